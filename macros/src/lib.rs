@@ -19,12 +19,10 @@ pub fn call(
 ///   valid block number.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
-/// - Note: For simplicity, we assume that the system pallet is not callable.
 /// - `enum RuntimeCall` - an "outer"-enum representing the accumulation of all possible calls to
-///   all pallets. The system pallet is not included.
+///   all pallets, including the `system::Call` calls like `remark`.
 /// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. Basic
-///   logic like incrementing the nonce of the user is included in the generated code. The system
-///   pallet is not included.
+///   logic like incrementing the nonce of the user is included in the generated code.
 #[proc_macro_attribute]
 pub fn runtime(
 	attr: proc_macro::TokenStream,
@@ -26,8 +26,8 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 
 			// Execute a block of extrinsics. Increments the block number.
 			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
-				self.system.inc_block_number();
-				if block.header.block_number != self.system.block_number() {
+				self.system.set_block_number();
+				if block.header.block_number != self.system.get_block_number() {
 					return Err(&"block number does not match what is expected")
 				}
 				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
@@ -52,6 +52,8 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
 		#[allow(non_camel_case_types)]
 		pub enum RuntimeCall {
+			// System is not in the list of pallets, and its `Call` is not generic, so we add it here.
+			system(system::Call),
 			#( #pallet_names(#pallet_names::Call<#runtime_struct>) ),*
 		}
 
@@ -71,6 +73,11 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 				// This match statement will allow us to correctly route `RuntimeCall`s
 				// to the appropriate pallet level call.
 				match runtime_call {
+					// Unlike the pallets generated with `#[macros::call]`, system takes an `Origin`,
+					// and extrinsics are always signed by their caller.
+					RuntimeCall::system(call) => {
+						self.system.dispatch(crate::support::Origin::Signed(caller), call)?;
+					}
 					#(
 						RuntimeCall::#pallet_names(call) => {
 							self.#pallet_names.dispatch(caller, call)?;
@@ -89,3 +96,30 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 	}
 	.into()
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{super::parse::RuntimeDef, expand_runtime};
+
+	#[test]
+	fn system_calls_are_dispatched_with_a_signed_origin() {
+		let runtime: syn::Item = syn::parse_quote! {
+			pub struct Runtime {
+				system: system::Pallet<Self>,
+				balances: balances::Pallet<Self>,
+			}
+		};
+		let def = RuntimeDef::try_from(runtime).unwrap();
+		let expanded: syn::File = syn::parse2(expand_runtime(def)).unwrap();
+		let expanded = quote::quote!(#expanded).to_string();
+
+		let system_dispatch = quote::quote! {
+			self.system.dispatch(crate::support::Origin::Signed(caller), call)?;
+		};
+		let balances_dispatch = quote::quote! {
+			self.balances.dispatch(caller, call)?;
+		};
+		assert!(expanded.contains(&system_dispatch.to_string()));
+		assert!(expanded.contains(&balances_dispatch.to_string()));
+	}
+}
@@ -39,7 +39,7 @@ impl RuntimeDef {
 
 /// This function checks that the `system` pallet is the first pallet included in the `Runtime`
 /// struct. We make many assumptions about the `system` pallet in order to keep these macros simple.
-/// For example, we assume that the system pallet has no callable functions, and that it contains
+/// For example, we assume that the system pallet has a non-generic `Call` enum, and that it contains
 /// specific functions like incrementing the block number and a user's nonce.
 ///
 /// You can consider these macros to be tightly coupled to the logic of the `system` pallet.
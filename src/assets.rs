@@ -0,0 +1,355 @@
+use crate::{
+	support::{DispatchResult, Origin},
+	system,
+};
+use num::{CheckedAdd, CheckedSub, Zero};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub trait Config: crate::system::Config {
+	type AssetId: Ord + Clone;
+	type AssetBalance: Zero + CheckedSub + CheckedAdd + Copy + PartialOrd;
+}
+
+/// The details of an asset which are kept up to date as it is used.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetDetails<AccountId, Balance> {
+	/// The account which created the asset, and is allowed to mint and burn it.
+	pub admin: AccountId,
+	/// The total amount of the asset held by all accounts.
+	pub supply: Balance,
+	/// The minimum balance an account must hold of this asset. Like the existential deposit of
+	/// the native currency, smaller balances are removed.
+	pub min_balance: Balance,
+}
+
+/// Descriptive metadata of an asset, set when it is created.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssetMetadata {
+	pub name: String,
+	pub symbol: String,
+	pub decimals: u8,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "T::AssetId: Serialize + DeserializeOwned, T::AccountId: Serialize + \
+	DeserializeOwned, T::AssetBalance: Serialize + DeserializeOwned")]
+pub struct Pallet<T: Config> {
+	#[serde(with = "crate::support::storage_map")]
+	assets: BTreeMap<T::AssetId, AssetDetails<T::AccountId, T::AssetBalance>>,
+	#[serde(with = "crate::support::storage_map")]
+	metadata: BTreeMap<T::AssetId, AssetMetadata>,
+	/// The balance of each account, per asset.
+	#[serde(with = "crate::support::storage_map")]
+	accounts: BTreeMap<(T::AssetId, T::AccountId), T::AssetBalance>,
+}
+
+impl<T: Config> Pallet<T> {
+	pub fn new() -> Self {
+		Self { assets: BTreeMap::new(), metadata: BTreeMap::new(), accounts: BTreeMap::new() }
+	}
+
+	pub fn asset(&self, id: &T::AssetId) -> Option<AssetDetails<T::AccountId, T::AssetBalance>> {
+		self.assets.get(id).cloned()
+	}
+
+	#[cfg(test)]
+	pub fn metadata(&self, id: &T::AssetId) -> Option<AssetMetadata> {
+		self.metadata.get(id).cloned()
+	}
+
+	pub fn balance(&self, id: &T::AssetId, who: &T::AccountId) -> T::AssetBalance {
+		*self
+			.accounts
+			.get(&(id.clone(), who.clone()))
+			.unwrap_or(&T::AssetBalance::zero())
+	}
+
+	/// Create a new asset `id`, administered by `admin`.
+	pub fn create(
+		&mut self,
+		admin: T::AccountId,
+		id: T::AssetId,
+		min_balance: T::AssetBalance,
+		metadata: AssetMetadata,
+	) -> DispatchResult {
+		if self.assets.contains_key(&id) {
+			return Err("asset already exists");
+		}
+		if min_balance.is_zero() {
+			return Err("minimum balance must be greater than zero");
+		}
+		self.assets.insert(
+			id.clone(),
+			AssetDetails { admin, supply: T::AssetBalance::zero(), min_balance },
+		);
+		self.metadata.insert(id, metadata);
+		Ok(())
+	}
+
+	/// Create `amount` of asset `id` in the account of `to`. Only the asset admin can mint.
+	pub fn mint(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		caller: T::AccountId,
+		id: T::AssetId,
+		to: T::AccountId,
+		amount: T::AssetBalance,
+	) -> DispatchResult {
+		let mut details = self.ensure_admin(&caller, &id)?;
+		details.supply = details.supply.checked_add(&amount).ok_or("Overflow")?;
+		let new_balance = self.balance(&id, &to).checked_add(&amount).ok_or("Overflow")?;
+		if new_balance < details.min_balance {
+			return Err("balance below minimum balance");
+		}
+
+		self.insert_account(system, &id, &to, new_balance)?;
+		self.assets.insert(id, details);
+		Ok(())
+	}
+
+	/// Destroy `amount` of asset `id` from the account of `from`. Only the asset admin can burn.
+	pub fn burn(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		caller: T::AccountId,
+		id: T::AssetId,
+		from: T::AccountId,
+		amount: T::AssetBalance,
+	) -> DispatchResult {
+		let mut details = self.ensure_admin(&caller, &id)?;
+		let new_balance =
+			self.balance(&id, &from).checked_sub(&amount).ok_or("Not enough balance")?;
+		details.supply = details.supply.checked_sub(&amount).ok_or("Underflow")?;
+
+		self.insert_account(system, &id, &from, new_balance)?;
+		self.assets.insert(id.clone(), details);
+		self.remove_dust(system, &id, &from);
+		Ok(())
+	}
+
+	/// Transfer `amount` of asset `id` from `caller` to `to`. If this leaves `caller` below the
+	/// minimum balance of the asset, the remaining dust is burned.
+	pub fn transfer(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		caller: T::AccountId,
+		id: T::AssetId,
+		to: T::AccountId,
+		amount: T::AssetBalance,
+	) -> DispatchResult {
+		let details = self.assets.get(&id).ok_or("asset does not exist")?;
+		let new_balance_from =
+			self.balance(&id, &caller).checked_sub(&amount).ok_or("Not enough balance")?;
+		if caller == to {
+			return Ok(());
+		}
+		let new_balance_to = self.balance(&id, &to).checked_add(&amount).ok_or("Overflow")?;
+		if new_balance_to < details.min_balance {
+			return Err("balance below minimum balance");
+		}
+
+		self.insert_account(system, &id, &to, new_balance_to)?;
+		self.insert_account(system, &id, &caller, new_balance_from)?;
+		self.remove_dust(system, &id, &caller);
+		Ok(())
+	}
+
+	fn ensure_admin(
+		&self,
+		who: &T::AccountId,
+		id: &T::AssetId,
+	) -> Result<AssetDetails<T::AccountId, T::AssetBalance>, &'static str> {
+		let details = self.asset(id).ok_or("asset does not exist")?;
+		if details.admin != *who {
+			return Err("not the asset admin");
+		}
+		Ok(details)
+	}
+
+	/// Store the balance of `who` in asset `id`. A new asset account adds a consumer to `who`, so
+	/// it cannot be reaped while it holds assets. Every account is stored through here, so
+	/// `remove_dust` only ever drops a consumer this pallet added.
+	fn insert_account(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		id: &T::AssetId,
+		who: &T::AccountId,
+		balance: T::AssetBalance,
+	) -> DispatchResult {
+		let key = (id.clone(), who.clone());
+		if !self.accounts.contains_key(&key) {
+			system.inc_consumers(who)?;
+		}
+		self.accounts.insert(key, balance);
+		Ok(())
+	}
+
+	/// Remove the balance of `who` in asset `id` if it is below the minimum balance, burning it.
+	fn remove_dust(&mut self, system: &mut system::Pallet<T>, id: &T::AssetId, who: &T::AccountId) {
+		let key = (id.clone(), who.clone());
+		let (Some(details), Some(balance)) = (self.assets.get_mut(id), self.accounts.get(&key))
+		else {
+			return;
+		};
+		if *balance < details.min_balance {
+			// Dust is always part of the supply, so this cannot underflow.
+			details.supply = details.supply.checked_sub(balance).unwrap_or(T::AssetBalance::zero());
+			self.accounts.remove(&key);
+			system.dec_consumers(who);
+		}
+	}
+}
+
+#[derive(Serialize)]
+#[serde(bound = "T::AccountId: Serialize, T::AssetId: Serialize, T::AssetBalance: Serialize")]
+pub enum Call<T: Config> {
+	Create { id: T::AssetId, min_balance: T::AssetBalance, metadata: AssetMetadata },
+	Mint { id: T::AssetId, to: T::AccountId, amount: T::AssetBalance },
+	Burn { id: T::AssetId, from: T::AccountId, amount: T::AssetBalance },
+	Transfer { id: T::AssetId, to: T::AccountId, amount: T::AssetBalance },
+}
+
+impl<T: Config> Pallet<T> {
+	pub fn dispatch(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		origin: Origin<T::AccountId>,
+		call: Call<T>,
+	) -> DispatchResult {
+		let caller = origin.ensure_signed()?;
+		match call {
+			Call::Create { id, min_balance, metadata } => {
+				self.create(caller, id, min_balance, metadata)?;
+			},
+			Call::Mint { id, to, amount } => {
+				self.mint(system, caller, id, to, amount)?;
+			},
+			Call::Burn { id, from, amount } => {
+				self.burn(system, caller, id, from, amount)?;
+			},
+			Call::Transfer { id, to, amount } => {
+				self.transfer(system, caller, id, to, amount)?;
+			},
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod assets_tests {
+	use super::{AssetMetadata, Pallet};
+	use crate::system;
+
+	struct TestConfig;
+	impl crate::system::Config for TestConfig {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		const BLOCK_HASH_COUNT: u32 = 250;
+		type RuntimeEvent = crate::system::Event<&'static str>;
+	}
+	impl super::Config for TestConfig {
+		type AssetId = u32;
+		type AssetBalance = u128;
+	}
+
+	/// A system pallet where the accounts used by the tests exist, so they can hold assets.
+	fn init_system() -> system::Pallet<TestConfig> {
+		let mut system = system::Pallet::new();
+		for who in ["alice", "bob"] {
+			system.inc_providers(&who);
+		}
+		system
+	}
+
+	fn usdc() -> AssetMetadata {
+		AssetMetadata { name: "USD Coin".to_string(), symbol: "USDC".to_string(), decimals: 6 }
+	}
+
+	#[test]
+	fn create_and_mint_assets() {
+		let mut system = init_system();
+		let mut assets = Pallet::<TestConfig>::new();
+
+		assert_eq!(
+			assets.create("alice", 1, 0, usdc()),
+			Err("minimum balance must be greater than zero")
+		);
+		assert_eq!(assets.create("alice", 1, 10, usdc()), Ok(()));
+		assert_eq!(assets.create("bob", 1, 10, usdc()), Err("asset already exists"));
+		assert_eq!(assets.metadata(&1), Some(usdc()));
+
+		assert_eq!(assets.mint(&mut system, "bob", 1, "bob", 100), Err("not the asset admin"));
+		assert_eq!(assets.mint(&mut system, "alice", 2, "bob", 100), Err("asset does not exist"));
+		assert_eq!(
+			assets.mint(&mut system, "alice", 1, "bob", 5),
+			Err("balance below minimum balance")
+		);
+		assert_eq!(assets.mint(&mut system, "alice", 1, "bob", 100), Ok(()));
+		assert_eq!(assets.balance(&1, &"bob"), 100);
+		assert_eq!(assets.asset(&1).unwrap().supply, 100);
+		// Holding an asset keeps the account alive, and only existing accounts can hold one.
+		assert!(!system.can_dec_provider(&"bob"));
+		assert_eq!(
+			assets.mint(&mut system, "alice", 1, "eve", 100),
+			Err("account has no providers")
+		);
+
+		assert_eq!(assets.burn(&mut system, "alice", 1, "bob", 95), Ok(()));
+		// The remaining 5 is below the minimum balance, so it is burned too.
+		assert_eq!(assets.balance(&1, &"bob"), 0);
+		assert_eq!(assets.asset(&1).unwrap().supply, 0);
+		assert_eq!(system.account(&"bob").consumers, 0);
+	}
+
+	#[test]
+	fn transfer_assets() {
+		let mut system = init_system();
+		let mut assets = Pallet::<TestConfig>::new();
+
+		assert_eq!(assets.create("alice", 1, 10, usdc()), Ok(()));
+		assert_eq!(assets.create("alice", 2, 1, usdc()), Ok(()));
+		assert_eq!(assets.mint(&mut system, "alice", 1, "alice", 100), Ok(()));
+
+		// Balances are kept per asset.
+		assert_eq!(assets.transfer(&mut system, "alice", 2, "bob", 10), Err("Not enough balance"));
+		assert_eq!(
+			assets.transfer(&mut system, "alice", 1, "bob", 5),
+			Err("balance below minimum balance")
+		);
+		assert_eq!(assets.transfer(&mut system, "alice", 1, "bob", 30), Ok(()));
+		assert_eq!(assets.balance(&1, &"alice"), 70);
+		assert_eq!(assets.balance(&1, &"bob"), 30);
+		assert_eq!(assets.balance(&2, &"bob"), 0);
+
+		// Transfers to yourself change nothing, but still need the funds.
+		assert_eq!(assets.transfer(&mut system, "bob", 1, "bob", 31), Err("Not enough balance"));
+		assert_eq!(assets.transfer(&mut system, "bob", 1, "bob", 30), Ok(()));
+		assert_eq!(assets.balance(&1, &"bob"), 30);
+
+		assert_eq!(assets.transfer(&mut system, "alice", 1, "bob", 65), Ok(()));
+		assert_eq!(assets.balance(&1, &"alice"), 0);
+		assert_eq!(assets.asset(&1).unwrap().supply, 95);
+		assert_eq!(system.account(&"alice").consumers, 0);
+		assert_eq!(system.account(&"bob").consumers, 1);
+	}
+
+	#[test]
+	fn zero_amounts_leave_other_consumers_alone() {
+		let mut system = init_system();
+		let mut assets = Pallet::<TestConfig>::new();
+		assert_eq!(assets.create("alice", 1, 10, usdc()), Ok(()));
+		assert_eq!(assets.mint(&mut system, "alice", 1, "alice", 100), Ok(()));
+		// Another pallet, e.g. a claim deposit, holds a consumer on bob.
+		assert_eq!(system.inc_consumers(&"bob"), Ok(()));
+
+		// bob holds none of the asset, so these move nothing, and must not release the consumer.
+		assert_eq!(assets.transfer(&mut system, "bob", 1, "alice", 0), Ok(()));
+		assert_eq!(assets.burn(&mut system, "alice", 1, "bob", 0), Ok(()));
+		assert_eq!(system.account(&"bob").consumers, 1);
+		assert!(!system.can_dec_provider(&"bob"));
+		assert_eq!(assets.balance(&1, &"alice"), 100);
+		assert_eq!(assets.asset(&1).unwrap().supply, 100);
+	}
+}
@@ -1,106 +1,1011 @@
-use num::{CheckedAdd, CheckedSub, Zero};
-use std::collections::BTreeMap;
+use crate::{
+	support::{Currency, DispatchResult, ExistenceRequirement, Origin, ReservableCurrency},
+	system,
+};
+use num::{CheckedAdd, CheckedMul, CheckedSub, Zero};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt::Debug};
 
 pub trait Config: crate::system::Config {
 	// type AccountId: Ord + Clone;
-	type Balance: Zero + CheckedSub + CheckedAdd + Copy;
+	type Balance: Zero + CheckedSub + CheckedAdd + CheckedMul + Copy + PartialOrd + Debug;
+	/// The minimum balance an account must hold to exist. Accounts which fall below it are reaped,
+	/// and accounts cannot be created with less than it.
+	const EXISTENTIAL_DEPOSIT: Self::Balance;
 }
-#[derive(Debug)]
+/// The balance of a single account, split into the part it can freely spend and the part which is
+/// reserved, e.g. as a deposit held by another pallet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountData<Balance> {
+	pub free: Balance,
+	pub reserved: Balance,
+}
+
+/// The identifier of a balance lock, so the pallet which set it can later update or remove it.
+pub type LockIdentifier = [u8; 8];
+
+/// The kinds of withdrawal a balance lock applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Reasons {
+	/// Transferring funds to another account.
+	Transfer,
+	/// Reserving funds, e.g. as a deposit.
+	Reserve,
+	All,
+}
+
+impl Reasons {
+	fn applies_to(self, reason: Reasons) -> bool {
+		self == Reasons::All || self == reason
+	}
+}
+
+/// A lock on the free balance of an account: withdrawals for any of `reasons` may not take the
+/// free balance below `amount`. Locks overlap rather than stack, so the largest one applies.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceLock<Balance> {
+	pub id: LockIdentifier,
+	pub amount: Balance,
+	pub reasons: Reasons,
+}
+
+/// Where repatriated funds end up in the beneficiary account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BalanceStatus {
+	Free,
+	Reserved,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(
+	bound = "T::AccountId: Serialize + DeserializeOwned, T::Balance: Serialize + DeserializeOwned"
+)]
 pub struct Pallet<T: Config> {
-	balances: BTreeMap<T::AccountId, T::Balance>,
+	#[serde(with = "crate::support::storage_map")]
+	balances: BTreeMap<T::AccountId, AccountData<T::Balance>>,
+	/// The sum of all balances. Every function which creates or destroys funds keeps this in sync.
+	total_issuance: T::Balance,
+	#[serde(with = "crate::support::storage_map")]
+	locks: BTreeMap<T::AccountId, Vec<BalanceLock<T::Balance>>>,
+	/// How much a spender may still transfer on behalf of an owner, keyed by `(owner, spender)`.
+	#[serde(with = "crate::support::storage_map")]
+	approvals: BTreeMap<(T::AccountId, T::AccountId), T::Balance>,
 }
 
 impl<T: Config> Pallet<T> {
 	pub fn new() -> Self {
-		Self { balances: BTreeMap::new() }
+		Self {
+			balances: BTreeMap::new(),
+			total_issuance: T::Balance::zero(),
+			locks: BTreeMap::new(),
+			approvals: BTreeMap::new(),
+		}
 	}
 
-	pub fn set_balance(&mut self, who: &T::AccountId, value: T::Balance) {
-		self.balances.insert(who.clone(), value);
+	/// Force the free balance of `who` to `value`, adjusting the total issuance by the difference.
+	pub fn set_balance(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		who: &T::AccountId,
+		value: T::Balance,
+	) -> DispatchResult {
+		let mut account = self.account(who);
+		let total_issuance = self
+			.total_issuance
+			.checked_sub(&account.free)
+			.and_then(|issuance| issuance.checked_add(&value))
+			.ok_or("Overflow")?;
+		account.free = value;
+		if Self::total(&account).ok_or("Overflow")? < T::EXISTENTIAL_DEPOSIT {
+			return Err("balance below existential deposit");
+		}
+
+		self.insert_account(system, who, account);
+		self.total_issuance = total_issuance;
+		Ok(())
 	}
 
+	/// The free balance of `who`, which is what transfers can spend.
 	pub fn balance(&self, who: &T::AccountId) -> T::Balance {
-		*self.balances.get(who).unwrap_or(&T::Balance::zero())
+		self.account(who).free
 	}
 
+	pub fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+		self.account(who).reserved
+	}
+
+	/// The free and reserved balance of `who` combined.
+	pub fn total_balance(&self, who: &T::AccountId) -> T::Balance {
+		// No account can hold more than the total issuance, so this cannot overflow.
+		Self::total(&self.account(who)).unwrap_or(T::Balance::zero())
+	}
+
+	pub fn total_issuance(&self) -> T::Balance {
+		self.total_issuance
+	}
+
+	/// Transfer `amount` from `from` to `to`. If this leaves `from` below the existential deposit,
+	/// the account is reaped.
 	pub fn transfer(
 		&mut self,
+		system: &mut system::Pallet<T>,
 		from: &T::AccountId,
 		to: &T::AccountId,
 		amount: T::Balance,
-	) -> crate::support::DispatchResult {
-		let balance_from = self.balance(from);
-		let balance_to = self.balance(to);
+	) -> DispatchResult {
+		self.ensure_can_reap(system, from, amount)?;
+		self.move_funds(system, from, to, amount)?;
+		self.reap_if_dust(system, from);
+		Ok(())
+	}
 
-		let new_balance_from = balance_from.checked_sub(&amount).ok_or("Not enough balance")?;
-		let new_balance_to = balance_to.checked_add(&amount).ok_or("Overflow")?;
+	/// Like `transfer`, but fails rather than leave `from` below the existential deposit.
+	pub fn transfer_keep_alive(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		from: &T::AccountId,
+		to: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let new_total_from =
+			self.total_balance(from).checked_sub(&amount).ok_or("Not enough balance")?;
+		if new_total_from < T::EXISTENTIAL_DEPOSIT {
+			return Err("transfer would kill account");
+		}
+		self.move_funds(system, from, to, amount)
+	}
 
-		self.set_balance(from, new_balance_from);
-		self.set_balance(to, new_balance_to);
+	/// Create `amount` new funds in the account of `to`.
+	pub fn mint(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		to: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let mut account = self.account(to);
+		account.free = account.free.checked_add(&amount).ok_or("Overflow")?;
+		if Self::total(&account).ok_or("Overflow")? < T::EXISTENTIAL_DEPOSIT {
+			return Err("balance below existential deposit");
+		}
+		let total_issuance = self.total_issuance.checked_add(&amount).ok_or("Overflow")?;
 
+		self.insert_account(system, to, account);
+		self.total_issuance = total_issuance;
 		Ok(())
 	}
-}
 
-pub enum Call<T: Config> {
-	Transfer { to: T::AccountId, amount: T::Balance },
-}
+	/// Destroy `amount` funds from the free balance of `from`, reaping it if it falls below the
+	/// existential deposit.
+	pub fn burn(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		from: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let mut account = self.account(from);
+		account.free = account.free.checked_sub(&amount).ok_or("Not enough balance")?;
+		let total_issuance = self.total_issuance.checked_sub(&amount).ok_or("Underflow")?;
+		self.ensure_can_reap(system, from, amount)?;
 
-impl<T: Config> crate::support::Dispatch for Pallet<T> {
-	type Caller = T::AccountId;
-	type Call = Call<T>;
-	fn dispatch(
+		self.balances.insert(from.clone(), account);
+		self.total_issuance = total_issuance;
+		self.reap_if_dust(system, from);
+		Ok(())
+	}
+
+	/// Move `amount` from the free balance of `who` to its reserved balance. Only existing accounts
+	/// can reserve funds. Reserving zero does nothing, so pallets configured with a zero deposit
+	/// work without special cases.
+	pub fn reserve(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		if amount.is_zero() {
+			return Ok(());
+		}
+		if !self.balances.contains_key(who) {
+			return Err("account does not exist");
+		}
+		let mut account = self.account(who);
+		account.free = account.free.checked_sub(&amount).ok_or("Not enough balance")?;
+		self.ensure_can_withdraw(who, amount, Reasons::Reserve)?;
+		account.reserved = account.reserved.checked_add(&amount).ok_or("Overflow")?;
+		self.balances.insert(who.clone(), account);
+		Ok(())
+	}
+
+	/// Move up to `amount` from the reserved balance of `who` back to its free balance. Returns the
+	/// part of `amount` which could not be unreserved.
+	pub fn unreserve(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+		let mut account = self.account(who);
+		let actual = Self::min(amount, account.reserved);
+		if actual.is_zero() {
+			return amount;
+		}
+		account.reserved = account.reserved - actual;
+		// The reserved funds were already part of this account, so this cannot overflow.
+		account.free = account.free.checked_add(&actual).unwrap_or(account.free);
+		self.balances.insert(who.clone(), account);
+		amount - actual
+	}
+
+	/// Destroy up to `amount` from the reserved balance of `who`, reaping the account if it falls
+	/// below the existential deposit. An account other pallets still depend on is never taken below
+	/// the existential deposit. Returns the part of `amount` which could not be slashed.
+	pub fn slash_reserved(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		who: &T::AccountId,
+		amount: T::Balance,
+	) -> T::Balance {
+		let mut account = self.account(who);
+		let actual = Self::min(self.removable(system, who, amount), account.reserved);
+		if actual.is_zero() {
+			return amount;
+		}
+		account.reserved = account.reserved - actual;
+		self.balances.insert(who.clone(), account);
+		self.total_issuance = self.total_issuance - actual;
+		self.reap_if_dust(system, who);
+		amount - actual
+	}
+
+	/// Move up to `amount` from the reserved balance of `slashed` to `beneficiary`, where it is
+	/// credited as free or reserved balance depending on `status`. `slashed` is reaped if it falls
+	/// below the existential deposit, or kept at it if other pallets still depend on it. Returns
+	/// the part of `amount` which could not be moved.
+	pub fn repatriate_reserved(
 		&mut self,
-		caller: Self::Caller,
-		call: Self::Call,
-	) -> crate::support::DispatchResult {
+		system: &mut system::Pallet<T>,
+		slashed: &T::AccountId,
+		beneficiary: &T::AccountId,
+		amount: T::Balance,
+		status: BalanceStatus,
+	) -> Result<T::Balance, &'static str> {
+		if slashed == beneficiary {
+			return Ok(match status {
+				BalanceStatus::Free => self.unreserve(slashed, amount),
+				BalanceStatus::Reserved => {
+					amount - Self::min(amount, self.reserved_balance(slashed))
+				},
+			});
+		}
+
+		let mut from = self.account(slashed);
+		let mut to = self.account(beneficiary);
+		let actual = Self::min(self.removable(system, slashed, amount), from.reserved);
+		if actual.is_zero() {
+			return Ok(amount);
+		}
+		from.reserved = from.reserved - actual;
+		match status {
+			BalanceStatus::Free => to.free = to.free.checked_add(&actual).ok_or("Overflow")?,
+			BalanceStatus::Reserved => {
+				to.reserved = to.reserved.checked_add(&actual).ok_or("Overflow")?
+			},
+		}
+		if Self::total(&to).ok_or("Overflow")? < T::EXISTENTIAL_DEPOSIT {
+			return Err("balance below existential deposit");
+		}
+
+		self.balances.insert(slashed.clone(), from);
+		self.insert_account(system, beneficiary, to);
+		self.reap_if_dust(system, slashed);
+		Ok(amount - actual)
+	}
+
+	/// Set a lock with the given `id` on the free balance of `who`, replacing any existing lock
+	/// with the same `id`.
+	pub fn set_lock(
+		&mut self,
+		id: LockIdentifier,
+		who: &T::AccountId,
+		amount: T::Balance,
+		reasons: Reasons,
+	) {
+		let locks = self.locks.entry(who.clone()).or_default();
+		locks.retain(|lock| lock.id != id);
+		locks.push(BalanceLock { id, amount, reasons });
+	}
+
+	pub fn remove_lock(&mut self, id: LockIdentifier, who: &T::AccountId) {
+		if let Some(locks) = self.locks.get_mut(who) {
+			locks.retain(|lock| lock.id != id);
+			if locks.is_empty() {
+				self.locks.remove(who);
+			}
+		}
+	}
+
+	/// Allow `spender` to transfer up to `amount` from the free balance of `owner`, replacing any
+	/// previous approval.
+	pub fn approve_transfer(
+		&mut self,
+		owner: &T::AccountId,
+		spender: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		if owner == spender {
+			return Err("cannot approve transfers to yourself");
+		}
+		let key = (owner.clone(), spender.clone());
+		if amount.is_zero() {
+			self.approvals.remove(&key);
+		} else {
+			self.approvals.insert(key, amount);
+		}
+		Ok(())
+	}
+
+	pub fn cancel_approval(
+		&mut self,
+		owner: &T::AccountId,
+		spender: &T::AccountId,
+	) -> DispatchResult {
+		self.approvals
+			.remove(&(owner.clone(), spender.clone()))
+			.ok_or("no approval to cancel")?;
+		Ok(())
+	}
+
+	pub fn allowance(&self, owner: &T::AccountId, spender: &T::AccountId) -> T::Balance {
+		*self
+			.approvals
+			.get(&(owner.clone(), spender.clone()))
+			.unwrap_or(&T::Balance::zero())
+	}
+
+	/// Transfer `amount` from `owner` to `to` on behalf of `spender`, spending from the allowance
+	/// `owner` approved for `spender`.
+	pub fn transfer_approved(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		spender: &T::AccountId,
+		owner: &T::AccountId,
+		to: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let remaining = self
+			.allowance(owner, spender)
+			.checked_sub(&amount)
+			.ok_or("amount exceeds allowance")?;
+		self.transfer(system, owner, to, amount)?;
+
+		let key = (owner.clone(), spender.clone());
+		if remaining.is_zero() {
+			self.approvals.remove(&key);
+		} else if self.approvals.contains_key(&key) {
+			// The approval is gone if the transfer reaped `owner`.
+			self.approvals.insert(key, remaining);
+		}
+		Ok(())
+	}
+
+	pub fn locks(&self, who: &T::AccountId) -> Vec<BalanceLock<T::Balance>> {
+		self.locks.get(who).cloned().unwrap_or_default()
+	}
+
+	/// The part of the free balance of `who` which cannot be withdrawn for `reason`.
+	pub fn frozen_balance(&self, who: &T::AccountId, reason: Reasons) -> T::Balance {
+		self.locks
+			.get(who)
+			.into_iter()
+			.flatten()
+			.filter(|lock| lock.reasons.applies_to(reason))
+			.fold(T::Balance::zero(), |frozen, lock| Self::max(frozen, lock.amount))
+	}
+
+	/// Check that withdrawing `amount` from the free balance of `who` for `reason` does not break
+	/// any of its locks.
+	fn ensure_can_withdraw(
+		&self,
+		who: &T::AccountId,
+		amount: T::Balance,
+		reason: Reasons,
+	) -> DispatchResult {
+		let new_free = self.balance(who).checked_sub(&amount).ok_or("Not enough balance")?;
+		if new_free < self.frozen_balance(who, reason) {
+			return Err("funds are locked");
+		}
+		Ok(())
+	}
+
+	fn account(&self, who: &T::AccountId) -> AccountData<T::Balance> {
+		self.balances
+			.get(who)
+			.copied()
+			.unwrap_or(AccountData { free: T::Balance::zero(), reserved: T::Balance::zero() })
+	}
+
+	fn total(account: &AccountData<T::Balance>) -> Option<T::Balance> {
+		account.free.checked_add(&account.reserved)
+	}
+
+	fn min(a: T::Balance, b: T::Balance) -> T::Balance {
+		if a < b {
+			a
+		} else {
+			b
+		}
+	}
+
+	fn max(a: T::Balance, b: T::Balance) -> T::Balance {
+		if a < b {
+			b
+		} else {
+			a
+		}
+	}
+
+	/// Store `account` for `who`, adding a provider to its account in the system pallet if this
+	/// creates it.
+	fn insert_account(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		who: &T::AccountId,
+		account: AccountData<T::Balance>,
+	) {
+		if !self.balances.contains_key(who) {
+			system.inc_providers(who);
+		}
+		self.balances.insert(who.clone(), account);
+	}
+
+	/// Check that taking `amount` from `who` would not reap an account which other pallets still
+	/// depend on.
+	fn ensure_can_reap(
+		&self,
+		system: &system::Pallet<T>,
+		who: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let new_total = self.total_balance(who).checked_sub(&amount).ok_or("Not enough balance")?;
+		if new_total < T::EXISTENTIAL_DEPOSIT && !system.can_dec_provider(who) {
+			return Err("account is still in use by other pallets");
+		}
+		Ok(())
+	}
+
+	/// The part of `amount` which can be taken from `who` without leaving it below the existential
+	/// deposit while other pallets still depend on it, when it could not be reaped.
+	fn removable(
+		&self,
+		system: &system::Pallet<T>,
+		who: &T::AccountId,
+		amount: T::Balance,
+	) -> T::Balance {
+		if system.can_dec_provider(who) {
+			return amount;
+		}
+		let spare = self.total_balance(who).checked_sub(&T::EXISTENTIAL_DEPOSIT);
+		Self::min(amount, spare.unwrap_or(T::Balance::zero()))
+	}
+
+	/// Move `amount` from the free balance of `from` to the free balance of `to` without changing
+	/// the total issuance. The caller decides what happens if `from` ends up below the existential
+	/// deposit.
+	fn move_funds(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		from: &T::AccountId,
+		to: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		self.ensure_can_withdraw(from, amount, Reasons::Transfer)?;
+		if from == to {
+			return Ok(());
+		}
+		let mut account_from = self.account(from);
+		let mut account_to = self.account(to);
+
+		account_from.free = account_from.free.checked_sub(&amount).ok_or("Not enough balance")?;
+		account_to.free = account_to.free.checked_add(&amount).ok_or("Overflow")?;
+		if Self::total(&account_to).ok_or("Overflow")? < T::EXISTENTIAL_DEPOSIT {
+			return Err("balance below existential deposit");
+		}
+
+		self.balances.insert(from.clone(), account_from);
+		self.insert_account(system, to, account_to);
+
+		Ok(())
+	}
+
+	/// If `who` holds less than the existential deposit, remove it entirely: any remaining dust is
+	/// burned, its locks and approvals are dropped, and its provider in the system pallet is
+	/// removed, which reaps the account there too unless something else keeps it alive. Accounts
+	/// which other pallets still depend on are kept.
+	fn reap_if_dust(&mut self, system: &mut system::Pallet<T>, who: &T::AccountId) {
+		match self.balances.get(who) {
+			Some(account)
+				if self.total_balance(who) < T::EXISTENTIAL_DEPOSIT
+					&& system.can_dec_provider(who) =>
+			{
+				// Dust is always part of the total issuance, so this cannot underflow.
+				let dust = account.free + account.reserved;
+				self.total_issuance = self.total_issuance - dust;
+				self.balances.remove(who);
+				self.locks.remove(who);
+				self.approvals.retain(|(owner, _), _| owner != who);
+				// Every account in this pallet holds a provider, and we checked it can be removed.
+				let _ = system.dec_providers(who);
+			},
+			_ => {},
+		}
+	}
+
+	/// Check the storage invariants of this pallet: the sum of all balances must be exactly the
+	/// total issuance, and no account may hold less than the existential deposit.
+	pub fn try_state(&self) -> DispatchResult {
+		let mut sum = T::Balance::zero();
+		for account in self.balances.values() {
+			let total = Self::total(account).ok_or("account balance overflows")?;
+			if total < T::EXISTENTIAL_DEPOSIT {
+				return Err("account below existential deposit");
+			}
+			sum = sum.checked_add(&total).ok_or("sum of balances overflows")?;
+		}
+		if sum != self.total_issuance {
+			return Err("sum of balances does not match total issuance");
+		}
+		Ok(())
+	}
+
+	/// Dispatch a call to this pallet. Unlike other pallets this needs access to the system
+	/// pallet, as transfers and burns can reap accounts.
+	pub fn dispatch(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		origin: Origin<T::AccountId>,
+		call: Call<T>,
+	) -> DispatchResult {
 		match call {
 			Call::Transfer { to, amount } => {
-				self.transfer(&caller, &to, amount)?;
+				let caller = origin.ensure_signed()?;
+				self.transfer(system, &caller, &to, amount)?;
+			},
+			Call::TransferKeepAlive { to, amount } => {
+				let caller = origin.ensure_signed()?;
+				self.transfer_keep_alive(system, &caller, &to, amount)?;
+			},
+			Call::SetBalance { who, amount } => {
+				origin.ensure_root()?;
+				self.set_balance(system, &who, amount)?;
+			},
+			Call::Mint { to, amount } => {
+				origin.ensure_root()?;
+				self.mint(system, &to, amount)?;
+			},
+			Call::Burn { from, amount } => {
+				origin.ensure_root()?;
+				self.burn(system, &from, amount)?;
+			},
+			Call::ApproveTransfer { spender, amount } => {
+				let caller = origin.ensure_signed()?;
+				self.approve_transfer(&caller, &spender, amount)?;
+			},
+			Call::TransferApproved { owner, to, amount } => {
+				let caller = origin.ensure_signed()?;
+				self.transfer_approved(system, &caller, &owner, &to, amount)?;
+			},
+			Call::CancelApproval { spender } => {
+				let caller = origin.ensure_signed()?;
+				self.cancel_approval(&caller, &spender)?;
 			},
 		}
 		Ok(())
 	}
 }
 
+impl<T: Config> Currency<T> for Pallet<T> {
+	type Balance = T::Balance;
+
+	fn free_balance(&self, who: &T::AccountId) -> T::Balance {
+		self.balance(who)
+	}
+
+	fn transfer(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		from: &T::AccountId,
+		to: &T::AccountId,
+		amount: T::Balance,
+		existence: ExistenceRequirement,
+	) -> DispatchResult {
+		match existence {
+			ExistenceRequirement::KeepAlive => self.transfer_keep_alive(system, from, to, amount),
+			ExistenceRequirement::AllowDeath => Pallet::transfer(self, system, from, to, amount),
+		}
+	}
+
+	fn withdraw(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		who: &T::AccountId,
+		amount: T::Balance,
+		existence: ExistenceRequirement,
+	) -> DispatchResult {
+		self.ensure_can_withdraw(who, amount, Reasons::Transfer)?;
+		if existence == ExistenceRequirement::KeepAlive {
+			let new_total =
+				self.total_balance(who).checked_sub(&amount).ok_or("Not enough balance")?;
+			if new_total < T::EXISTENTIAL_DEPOSIT {
+				return Err("withdrawal would kill account");
+			}
+		}
+		self.burn(system, who, amount)
+	}
+
+	fn deposit_creating(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		who: &T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		self.mint(system, who, amount)
+	}
+}
+
+impl<T: Config> ReservableCurrency<T> for Pallet<T> {
+	fn reserved_balance(&self, who: &T::AccountId) -> T::Balance {
+		Pallet::reserved_balance(self, who)
+	}
+
+	fn reserve(&mut self, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		Pallet::reserve(self, who, amount)
+	}
+
+	fn unreserve(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+		Pallet::unreserve(self, who, amount)
+	}
+}
+
+#[derive(Serialize)]
+#[serde(bound = "T::AccountId: Serialize, T::Balance: Serialize")]
+pub enum Call<T: Config> {
+	Transfer { to: T::AccountId, amount: T::Balance },
+	TransferKeepAlive { to: T::AccountId, amount: T::Balance },
+	SetBalance { who: T::AccountId, amount: T::Balance },
+	Mint { to: T::AccountId, amount: T::Balance },
+	Burn { from: T::AccountId, amount: T::Balance },
+	ApproveTransfer { spender: T::AccountId, amount: T::Balance },
+	TransferApproved { owner: T::AccountId, to: T::AccountId, amount: T::Balance },
+	CancelApproval { spender: T::AccountId },
+}
+
 #[cfg(test)]
 mod balance_tests {
-	use super::Pallet;
+	use super::{BalanceStatus, Call, Pallet, Reasons};
+	use crate::{support::Origin, system};
 
 	struct TestConfig;
 	impl crate::system::Config for TestConfig {
 		type AccountId = String;
 		type BlockNumber = u32;
 		type Nonce = u32;
+		const BLOCK_HASH_COUNT: u32 = 250;
+		type RuntimeEvent = crate::system::Event<String>;
 	}
 	impl super::Config for TestConfig {
 		type Balance = u128;
+		const EXISTENTIAL_DEPOSIT: u128 = 5;
 	}
 
 	#[test]
 	fn init_balances() {
+		let mut system = system::Pallet::<TestConfig>::new();
 		let mut balances = Pallet::<TestConfig>::new();
 
 		let alice = "alice".to_string();
 		let bob = "bob".to_string();
 
 		assert_eq!(balances.balance(&alice), 0);
-		balances.set_balance(&alice, 100);
+		assert_eq!(balances.set_balance(&mut system, &alice, 100), Ok(()));
 		assert_eq!(balances.balance(&alice), 100);
 		assert_eq!(balances.balance(&bob), 0);
+		assert_eq!(balances.total_issuance(), 100);
+		assert_eq!(
+			balances.set_balance(&mut system, &bob, 4),
+			Err("balance below existential deposit")
+		);
 	}
 
 	#[test]
 	fn transfer_ok() {
+		let mut system = system::Pallet::<TestConfig>::new();
 		let mut balances = Pallet::<TestConfig>::new();
 
 		let alice = "alice".to_string();
 		let bob = "bob".to_string();
 		assert_eq!(balances.balance(&bob), 0);
-		assert!(balances.transfer(&alice, &bob, 30).is_err());
-		balances.set_balance(&alice, 100);
-		assert_eq!(balances.transfer(&alice, &bob, 30), Ok(()));
+		assert!(balances.transfer(&mut system, &alice, &bob, 30).is_err());
+		assert_eq!(balances.set_balance(&mut system, &alice, 100), Ok(()));
+		assert_eq!(balances.transfer(&mut system, &alice, &bob, 30), Ok(()));
+		assert_eq!(balances.balance(&bob), 30);
+		assert_eq!(balances.balance(&alice), 70);
+		assert_eq!(balances.total_issuance(), 100);
+		assert_eq!(balances.try_state(), Ok(()));
+
+		// Transferring to yourself moves nothing, rather than crediting the amount twice.
+		assert_eq!(balances.transfer(&mut system, &alice, &alice, 30), Ok(()));
+		assert_eq!(balances.transfer_keep_alive(&mut system, &bob, &bob, 20), Ok(()));
+		assert_eq!(balances.balance(&alice), 70);
+		assert_eq!(balances.balance(&bob), 30);
+		assert_eq!(balances.transfer(&mut system, &alice, &alice, 71), Err("Not enough balance"));
+		assert_eq!(balances.total_issuance(), 100);
+		assert_eq!(balances.try_state(), Ok(()));
+	}
+
+	#[test]
+	fn mint_and_burn() {
+		let mut system = system::Pallet::<TestConfig>::new();
+		let mut balances = Pallet::<TestConfig>::new();
+
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let mint = || Call::Mint { to: alice.clone(), amount: 50 };
+
+		assert_eq!(
+			balances.dispatch(&mut system, Origin::Signed(bob.clone()), mint()),
+			Err("bad origin")
+		);
+		assert_eq!(balances.dispatch(&mut system, Origin::Root, mint()), Ok(()));
+		assert_eq!(balances.balance(&alice), 50);
+		assert_eq!(balances.total_issuance(), 50);
+
+		assert_eq!(balances.burn(&mut system, &alice, 60), Err("Not enough balance"));
+		assert_eq!(balances.burn(&mut system, &alice, 20), Ok(()));
+		assert_eq!(balances.balance(&alice), 30);
+		assert_eq!(balances.total_issuance(), 30);
+
+		assert_eq!(balances.set_balance(&mut system, &bob, 10), Ok(()));
+		assert_eq!(balances.set_balance(&mut system, &alice, 5), Ok(()));
+		assert_eq!(balances.total_issuance(), 15);
+		assert_eq!(balances.try_state(), Ok(()));
+
+		balances.total_issuance = 16;
+		assert_eq!(balances.try_state(), Err("sum of balances does not match total issuance"));
+	}
+
+	#[test]
+	fn existential_deposit() {
+		let mut system = system::Pallet::<TestConfig>::new();
+		let mut balances = Pallet::<TestConfig>::new();
+
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let charlie = "charlie".to_string();
+		assert_eq!(balances.set_balance(&mut system, &alice, 100), Ok(()));
+		system.inc_nonce(&alice);
+
+		// New accounts must receive at least the existential deposit.
+		assert_eq!(
+			balances.transfer(&mut system, &alice, &bob, 4),
+			Err("balance below existential deposit")
+		);
+		assert_eq!(balances.mint(&mut system, &bob, 4), Err("balance below existential deposit"));
+		assert_eq!(balances.transfer(&mut system, &alice, &bob, 50), Ok(()));
+
+		// Keep alive transfers refuse to leave the sender below the existential deposit.
+		assert_eq!(
+			balances.transfer_keep_alive(&mut system, &alice, &charlie, 47),
+			Err("transfer would kill account")
+		);
+		assert_eq!(balances.transfer_keep_alive(&mut system, &alice, &charlie, 45), Ok(()));
+
+		// Accounts other pallets depend on cannot be reaped.
+		assert!(system.account_exists(&charlie));
+		assert_eq!(system.inc_consumers(&alice), Ok(()));
+		assert_eq!(
+			balances.transfer(&mut system, &alice, &charlie, 2),
+			Err("account is still in use by other pallets")
+		);
+		system.dec_consumers(&alice);
+
+		// Otherwise the sender is reaped, and its dust burned.
+		assert_eq!(system.nonce(&alice), 1);
+		assert_eq!(balances.transfer(&mut system, &alice, &charlie, 2), Ok(()));
+		assert_eq!(balances.balance(&alice), 0);
+		assert_eq!(balances.balances.get(&alice), None);
+		assert!(!system.account_exists(&alice));
+		assert_eq!(system.nonce(&alice), 0);
+		assert_eq!(balances.total_issuance(), 97);
+
+		assert_eq!(balances.burn(&mut system, &charlie, 44), Ok(()));
+		assert_eq!(balances.balances.get(&charlie), None);
+		assert_eq!(balances.total_issuance(), 50);
+		assert_eq!(balances.try_state(), Ok(()));
+	}
+
+	#[test]
+	fn reserved_balances() {
+		let mut system = system::Pallet::<TestConfig>::new();
+		let mut balances = Pallet::<TestConfig>::new();
+
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		assert_eq!(balances.set_balance(&mut system, &alice, 100), Ok(()));
+
+		assert_eq!(balances.reserve(&alice, 101), Err("Not enough balance"));
+		assert_eq!(balances.reserve(&alice, 0), Ok(()));
+		assert_eq!(balances.reserve(&bob, 0), Ok(()));
+		assert_eq!(balances.reserve(&bob, 1), Err("account does not exist"));
+		assert_eq!(balances.balances.get(&bob), None);
+		assert_eq!(balances.reserve(&alice, 60), Ok(()));
+		assert_eq!(balances.balance(&alice), 40);
+		assert_eq!(balances.reserved_balance(&alice), 60);
+		assert_eq!(balances.total_balance(&alice), 100);
+
+		// Only free balance can be transferred.
+		assert_eq!(balances.transfer(&mut system, &alice, &bob, 50), Err("Not enough balance"));
+
+		assert_eq!(balances.unreserve(&alice, 10), 0);
+		assert_eq!(balances.balance(&alice), 50);
+		assert_eq!(balances.reserved_balance(&alice), 50);
+
+		assert_eq!(balances.slash_reserved(&mut system, &alice, 20), 0);
+		assert_eq!(balances.reserved_balance(&alice), 30);
+		assert_eq!(balances.total_issuance(), 80);
+
+		// New accounts must still receive at least the existential deposit.
+		assert_eq!(
+			balances.repatriate_reserved(&mut system, &alice, &bob, 4, BalanceStatus::Free),
+			Err("balance below existential deposit")
+		);
+		assert_eq!(
+			balances.repatriate_reserved(&mut system, &alice, &bob, 10, BalanceStatus::Free),
+			Ok(0)
+		);
+		assert_eq!(
+			balances.repatriate_reserved(&mut system, &alice, &bob, 25, BalanceStatus::Reserved),
+			Ok(5)
+		);
+		assert_eq!(balances.balance(&bob), 10);
+		assert_eq!(balances.reserved_balance(&bob), 20);
+		assert_eq!(balances.reserved_balance(&alice), 0);
+
+		// Unreserving more than is reserved returns the remainder.
+		assert_eq!(balances.unreserve(&bob, 25), 5);
 		assert_eq!(balances.balance(&bob), 30);
+		assert_eq!(balances.total_issuance(), 80);
+		assert_eq!(balances.try_state(), Ok(()));
+
+		// Slashing the last reserved funds of an account below the existential deposit reaps it.
+		assert_eq!(balances.reserve(&alice, 48), Ok(()));
+		assert_eq!(balances.slash_reserved(&mut system, &alice, 48), 0);
+		assert_eq!(balances.balances.get(&alice), None);
+		assert_eq!(balances.total_issuance(), 30);
+		assert_eq!(balances.try_state(), Ok(()));
+
+		// An account other pallets depend on is only slashed down to the existential deposit.
+		assert_eq!(system.inc_consumers(&bob), Ok(()));
+		assert_eq!(balances.reserve(&bob, 30), Ok(()));
+		assert_eq!(balances.slash_reserved(&mut system, &bob, 30), 5);
+		assert_eq!(balances.total_balance(&bob), 5);
+		assert_eq!(
+			balances.repatriate_reserved(&mut system, &bob, &alice, 5, BalanceStatus::Free),
+			Ok(5)
+		);
+		assert_eq!(balances.try_state(), Ok(()));
+		assert!(system.account_exists(&bob));
+	}
+
+	#[test]
+	fn balance_locks() {
+		let mut system = system::Pallet::<TestConfig>::new();
+		let mut balances = Pallet::<TestConfig>::new();
+
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		assert_eq!(balances.set_balance(&mut system, &alice, 100), Ok(()));
+
+		// Locks overlap, so only the largest lock for a reason applies.
+		balances.set_lock(*b"staking ", &alice, 60, Reasons::Transfer);
+		balances.set_lock(*b"democrac", &alice, 30, Reasons::All);
+		assert_eq!(balances.frozen_balance(&alice, Reasons::Transfer), 60);
+		assert_eq!(balances.frozen_balance(&alice, Reasons::Reserve), 30);
+
+		assert_eq!(balances.transfer(&mut system, &alice, &bob, 41), Err("funds are locked"));
+		assert_eq!(
+			balances.transfer_keep_alive(&mut system, &alice, &bob, 41),
+			Err("funds are locked")
+		);
+		assert_eq!(balances.transfer(&mut system, &alice, &bob, 40), Ok(()));
+		assert_eq!(balances.reserve(&alice, 31), Err("funds are locked"));
+		assert_eq!(balances.reserve(&alice, 30), Ok(()));
+
+		// Setting a lock with an existing id replaces it.
+		balances.set_lock(*b"staking ", &alice, 10, Reasons::Transfer);
+		assert_eq!(balances.locks(&alice).len(), 2);
+		assert_eq!(balances.frozen_balance(&alice, Reasons::Transfer), 30);
+
+		balances.remove_lock(*b"staking ", &alice);
+		balances.remove_lock(*b"democrac", &alice);
+		assert!(balances.locks(&alice).is_empty());
+		assert_eq!(balances.reserve(&alice, 10), Ok(()));
+	}
+
+	#[test]
+	fn delegated_transfers() {
+		let mut system = system::Pallet::<TestConfig>::new();
+		let mut balances = Pallet::<TestConfig>::new();
+
+		let alice = "alice".to_string();
+		let service = "service".to_string();
+		let shop = "shop".to_string();
+		assert_eq!(balances.set_balance(&mut system, &alice, 100), Ok(()));
+
+		let pull =
+			|amount| Call::TransferApproved { owner: alice.clone(), to: shop.clone(), amount };
+		assert_eq!(
+			balances.dispatch(&mut system, Origin::Signed(service.clone()), pull(10)),
+			Err("amount exceeds allowance")
+		);
+
+		let approve = Call::ApproveTransfer { spender: service.clone(), amount: 50 };
+		assert_eq!(balances.dispatch(&mut system, Origin::Signed(alice.clone()), approve), Ok(()));
+		assert_eq!(balances.allowance(&alice, &service), 50);
+
+		assert_eq!(
+			balances.dispatch(&mut system, Origin::Signed(service.clone()), pull(30)),
+			Ok(())
+		);
 		assert_eq!(balances.balance(&alice), 70);
+		assert_eq!(balances.balance(&shop), 30);
+		assert_eq!(balances.allowance(&alice, &service), 20);
+		assert_eq!(
+			balances.dispatch(&mut system, Origin::Signed(service.clone()), pull(30)),
+			Err("amount exceeds allowance")
+		);
+
+		// Spending the whole allowance removes the approval.
+		assert_eq!(
+			balances.dispatch(&mut system, Origin::Signed(service.clone()), pull(20)),
+			Ok(())
+		);
+		assert_eq!(balances.approvals.len(), 0);
+
+		assert_eq!(balances.approve_transfer(&alice, &service, 10), Ok(()));
+		assert_eq!(balances.cancel_approval(&alice, &service), Ok(()));
+		assert_eq!(balances.allowance(&alice, &service), 0);
+		assert_eq!(balances.cancel_approval(&alice, &service), Err("no approval to cancel"));
+	}
+
+	#[test]
+	fn currency_trait() {
+		use crate::support::{Currency, ExistenceRequirement::*, ReservableCurrency};
+
+		// Pallets only see the balances pallet through the currency traits.
+		fn take_deposit<C: ReservableCurrency<TestConfig>>(
+			currency: &mut C,
+			who: &String,
+			amount: C::Balance,
+		) -> crate::support::DispatchResult {
+			currency.reserve(who, amount)
+		}
+
+		let mut system = system::Pallet::<TestConfig>::new();
+		let mut balances = Pallet::<TestConfig>::new();
+
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		assert_eq!(
+			balances.deposit_creating(&mut system, &alice, 4),
+			Err("balance below existential deposit")
+		);
+		assert_eq!(balances.deposit_creating(&mut system, &alice, 100), Ok(()));
+		assert_eq!(take_deposit(&mut balances, &alice, 20), Ok(()));
+		assert_eq!(balances.free_balance(&alice), 80);
+		assert_eq!(ReservableCurrency::reserved_balance(&balances, &alice), 20);
+
+		assert_eq!(
+			Currency::transfer(&mut balances, &mut system, &alice, &bob, 50, KeepAlive),
+			Ok(())
+		);
+		assert_eq!(
+			balances.withdraw(&mut system, &bob, 48, KeepAlive),
+			Err("withdrawal would kill account")
+		);
+		assert_eq!(balances.withdraw(&mut system, &bob, 48, AllowDeath), Ok(()));
+		assert_eq!(balances.free_balance(&bob), 0);
+		assert_eq!(balances.total_issuance(), 50);
+		assert_eq!(ReservableCurrency::unreserve(&mut balances, &alice, 20), 0);
+		assert_eq!(balances.try_state(), Ok(()));
 	}
 }
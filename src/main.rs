@@ -1,75 +1,480 @@
+mod assets;
 mod balances;
+mod merkle;
+mod multisig;
 mod proof_of_existence;
+mod proxy;
+mod snapshot;
+mod state_diff;
+mod sudo;
 mod support;
 mod system;
+mod timestamp;
+mod utility;
+mod vesting;
 use crate::support::Dispatch;
+use serde::{Deserialize, Serialize};
+use std::{io, path::Path};
 
 mod types {
 	pub type Nonce = u32;
 	pub type BlockNumber = u32;
 	pub type AccountId = String;
 	pub type Balance = u128;
+	pub type AssetId = u32;
 
-	pub type Extrinsic = crate::support::Extrinsic<AccountId, crate::RuntimeCall>;
+	pub type Origin = crate::support::Origin<AccountId>;
+	/// Extrinsics without a caller are unsigned, like the inherent which sets the timestamp.
+	pub type Extrinsic = crate::support::Extrinsic<Option<AccountId>, crate::RuntimeCall>;
 	pub type Header = crate::support::Header<BlockNumber>;
 	pub type Block = crate::support::Block<Header, Extrinsic>;
 
-	pub type Content = &'static str;
+	pub type Content = crate::support::Hash;
 }
 
+#[derive(Serialize)]
 pub enum RuntimeCall {
+	System(system::Call),
 	// BalancesTransfer { to: types::AccountId, amount: types::Balance },
+	Timestamp(timestamp::Call),
 	Balances(balances::Call<Runtime>),
+	Assets(assets::Call<Runtime>),
 	ProofOfExistence(proof_of_existence::Call<Runtime>),
+	Vesting(vesting::Call<Runtime>),
+	Sudo(sudo::Call<Runtime>),
+	Multisig(multisig::Call<Runtime>),
+	Proxy(proxy::Call<Runtime>),
+	Utility(utility::Call<Runtime>),
 }
 
-#[derive(Debug)]
+/// The events of every pallet in the runtime.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RuntimeEvent {
+	System(system::Event<types::AccountId>),
+	Sudo(sudo::Event<types::AccountId>),
+	Multisig(multisig::Event<types::AccountId>),
+	Proxy(proxy::Event<types::AccountId, types::BlockNumber>),
+	Utility(utility::Event),
+}
+
+impl From<system::Event<types::AccountId>> for RuntimeEvent {
+	fn from(event: system::Event<types::AccountId>) -> Self {
+		RuntimeEvent::System(event)
+	}
+}
+
+impl From<sudo::Event<types::AccountId>> for RuntimeEvent {
+	fn from(event: sudo::Event<types::AccountId>) -> Self {
+		RuntimeEvent::Sudo(event)
+	}
+}
+
+impl From<multisig::Event<types::AccountId>> for RuntimeEvent {
+	fn from(event: multisig::Event<types::AccountId>) -> Self {
+		RuntimeEvent::Multisig(event)
+	}
+}
+
+impl From<proxy::Event<types::AccountId, types::BlockNumber>> for RuntimeEvent {
+	fn from(event: proxy::Event<types::AccountId, types::BlockNumber>) -> Self {
+		RuntimeEvent::Proxy(event)
+	}
+}
+
+impl From<utility::Event> for RuntimeEvent {
+	fn from(event: utility::Event) -> Self {
+		RuntimeEvent::Utility(event)
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Runtime {
 	system: system::Pallet<Self>,
+	timestamp: timestamp::Pallet<Self>,
 	balances: balances::Pallet<Self>,
+	assets: assets::Pallet<Self>,
 	proof_of_existence: proof_of_existence::Pallet<Self>,
+	vesting: vesting::Pallet<Self>,
+	sudo: sudo::Pallet<Self>,
+	multisig: multisig::Pallet<Self>,
+	proxy: proxy::Pallet<Self>,
 }
 impl system::Config for Runtime {
 	type AccountId = types::AccountId;
 	type BlockNumber = types::BlockNumber;
 	type Nonce = types::Nonce;
+	const BLOCK_HASH_COUNT: types::BlockNumber = 250;
+	type RuntimeEvent = RuntimeEvent;
+}
+impl timestamp::Config for Runtime {
+	const MINIMUM_PERIOD: support::Moment = 3_000;
 }
 impl balances::Config for Runtime {
 	type Balance = types::Balance;
+	const EXISTENTIAL_DEPOSIT: types::Balance = 10;
+}
+impl assets::Config for Runtime {
+	type AssetId = types::AssetId;
+	type AssetBalance = types::Balance;
 }
 impl proof_of_existence::Config for Runtime {
 	type Content = types::Content;
+	type Currency = balances::Pallet<Self>;
+	const CLAIM_DEPOSIT: types::Balance = 5;
+	const MAX_EXPIRIES_PER_BLOCK: usize = 16;
+	const MAX_CLAIMS_PER_OWNER: usize = 64;
+
+	fn hash(document: &[u8]) -> types::Content {
+		support::sha256(document)
+	}
+}
+impl vesting::Config for Runtime {
+	fn blocks_to_balance(blocks: types::BlockNumber) -> types::Balance {
+		blocks.into()
+	}
+}
+impl sudo::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+}
+impl multisig::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	type Currency = balances::Pallet<Self>;
+	const MULTISIG_DEPOSIT: types::Balance = 5;
+	const MAX_SIGNATORIES: usize = 16;
+
+	fn multi_account_id(signatories: &[types::AccountId], threshold: u16) -> types::AccountId {
+		let encoded = serde_json::to_vec(&(signatories, threshold))
+			.expect("a list of account ids and a number always encode to JSON");
+		let hash = support::sha256(&encoded);
+		hash.iter().map(|byte| format!("{:02x}", byte)).collect()
+	}
+}
+impl proxy::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	const MAX_PROXIES: usize = 32;
+	const MAX_PENDING: usize = 32;
+
+	fn filter(proxy_type: proxy::ProxyType, call: &RuntimeCall) -> bool {
+		match proxy_type {
+			proxy::ProxyType::Any => true,
+			proxy::ProxyType::Transfer => matches!(
+				call,
+				RuntimeCall::Balances(
+					balances::Call::Transfer { .. } | balances::Call::TransferKeepAlive { .. }
+				)
+			),
+			proxy::ProxyType::Claims => matches!(call, RuntimeCall::ProofOfExistence(_)),
+		}
+	}
+}
+impl utility::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
 }
 
 impl Runtime {
 	pub fn new() -> Self {
 		Self {
 			system: system::Pallet::new(),
+			timestamp: timestamp::Pallet::new(),
 			balances: balances::Pallet::new(),
+			assets: assets::Pallet::new(),
 			proof_of_existence: proof_of_existence::Pallet::new(),
+			vesting: vesting::Pallet::new(),
+			sudo: sudo::Pallet::new(),
+			multisig: multisig::Pallet::new(),
+			proxy: proxy::Pallet::new(),
 		}
 	}
 
+	/// Execute `block` on top of the current state. An invalid block, i.e. one with the wrong
+	/// number, a missing or failing inherent, or one which cannot be finalized, leaves the state
+	/// exactly as it was.
 	fn execute_block(&mut self, block: types::Block) -> support::DispatchResult {
-		self.system.set_block_number();
-		if self.system.get_block_number() != block.header.block_number {
+		if block.header.block_number != self.system.get_block_number() + 1 {
 			return Err("Wrong block number");
 		}
+		// The timestamp inherent must come first, and only once.
+		let is_inherent = |extrinsic: &types::Extrinsic| {
+			matches!(
+				extrinsic,
+				support::Extrinsic { caller: None, call: RuntimeCall::Timestamp(_) }
+			)
+		};
+		let inherents = block.extrinsics.iter().filter(|extrinsic| is_inherent(extrinsic)).count();
+		if inherents != 1 || !block.extrinsics.first().is_some_and(is_inherent) {
+			return Err("block must start with exactly one timestamp inherent");
+		}
+
+		// Failures from here on are only found by executing the block, so keep a snapshot to
+		// restore.
+		let snapshot = self.snapshot()?;
+		if let Err(error) = self.apply_block(block) {
+			self.restore(&snapshot)?;
+			return Err(error);
+		}
+		Ok(())
+	}
+
+	/// Encode the whole state, so it can be put back with `restore`. This copies every pallet's
+	/// storage, so each block and each `batch_all` costs O(total state) on top of its calls.
+	fn snapshot(&self) -> Result<Vec<u8>, &'static str> {
+		serde_json::to_vec(self).map_err(|_| "failed to serialize state")
+	}
+
+	/// Put back the state saved by `snapshot`, undoing every change made since.
+	fn restore(&mut self, snapshot: &[u8]) -> support::DispatchResult {
+		*self = serde_json::from_slice(snapshot).map_err(|_| "failed to restore state")?;
+		Ok(())
+	}
+
+	/// Execute a block which has already been checked by `execute_block`.
+	fn apply_block(&mut self, block: types::Block) -> support::DispatchResult {
+		self.system.set_block_number();
+		self.system.reset_events();
+		let extrinsic_hashes: Vec<support::Hash> =
+			block.extrinsics.iter().map(support::call_hash).collect::<Result<_, _>>()?;
 		for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
-			self.system.inc_nonce(&caller);
-			let _ = self.dispatch(caller, call).map_err(|e| {
+			let result = match caller {
+				// An account which does not exist could not pay for its extrinsic.
+				Some(caller) if !self.system.account_exists(&caller) => {
+					Err("account does not exist")
+				},
+				Some(caller) => {
+					self.system.inc_nonce(&caller);
+					self.dispatch(types::Origin::Signed(caller), call)
+				},
+				None => self.dispatch(types::Origin::None, call),
+			};
+			// A block with a failing inherent is invalid, unlike one with a failing transaction.
+			if i == 0 {
+				result?;
+			}
+			let _ = result.map_err(|e| {
 				eprintln!(
 					"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
 					block.header.block_number, i, e
 				)
 			});
 		}
+		self.timestamp.on_finalize()?;
+		self.proof_of_existence.on_finalize(&mut self.system, &mut self.balances);
+
+		// A block is identified by its parent, its header, what it contains and the state it leaves
+		// behind, so different blocks at the same height get different hashes.
+		let state = serde_json::to_vec(&*self).map_err(|_| "failed to serialize state")?;
+		let parts =
+			(self.system.parent_hash(), &block.header, extrinsic_hashes, support::sha256(&state));
+		let encoded = serde_json::to_vec(&parts).map_err(|_| "failed to encode block")?;
+		self.system.note_block_hash(support::sha256(&encoded));
+		Ok(())
+	}
+
+	/// Dispatch a call to the sudo pallet. `sudo` and `sudo_as` dispatch another call of the
+	/// runtime, so they are executed here, and only fail if the origin is not the sudo key.
+	fn dispatch_sudo(
+		&mut self,
+		origin: types::Origin,
+		call: sudo::Call<Self>,
+	) -> support::DispatchResult {
+		self.sudo.ensure_sudo(origin)?;
+		match call {
+			sudo::Call::Sudo { call } => {
+				let result = self.dispatch(types::Origin::Root, *call).map_err(String::from);
+				self.system.deposit_event(sudo::Event::Sudid { result });
+			},
+			sudo::Call::SudoAs { who, call } => {
+				let result = self.dispatch(types::Origin::Signed(who), *call).map_err(String::from);
+				self.system.deposit_event(sudo::Event::SudoAsDone { result });
+			},
+			sudo::Call::SetKey { new } => self.sudo.set_key(&mut self.system, new),
+		}
+		Ok(())
+	}
+
+	/// Dispatch a call to the multisig pallet. Once a call has enough approvals, it is dispatched
+	/// here from the multisig account, and the result only reported in an event.
+	fn dispatch_multisig(
+		&mut self,
+		origin: types::Origin,
+		call: multisig::Call<Self>,
+	) -> support::DispatchResult {
+		let who = origin.ensure_signed()?;
+		let (system, balances) = (&mut self.system, &mut self.balances);
+		match call {
+			multisig::Call::AsMulti { threshold, other_signatories, call } => {
+				let call_hash = support::call_hash(&call)?;
+				let approving = who.clone();
+				let multi = self.multisig.as_multi(
+					system,
+					balances,
+					who,
+					threshold,
+					other_signatories,
+					call_hash,
+				)?;
+				if let Some(multisig) = multi {
+					let origin = types::Origin::Signed(multisig.clone());
+					let result = self.dispatch(origin, *call).map_err(String::from);
+					self.system.deposit_event(multisig::Event::MultisigExecuted {
+						approving,
+						multisig,
+						call_hash,
+						result,
+					});
+				}
+			},
+			multisig::Call::ApproveAsMulti { threshold, other_signatories, call_hash } => {
+				self.multisig.approve_as_multi(
+					system,
+					balances,
+					who,
+					threshold,
+					other_signatories,
+					call_hash,
+				)?;
+			},
+			multisig::Call::CancelAsMulti { threshold, other_signatories, call_hash } => {
+				self.multisig.cancel_as_multi(
+					system,
+					balances,
+					who,
+					threshold,
+					other_signatories,
+					call_hash,
+				)?;
+			},
+		}
+		Ok(())
+	}
+
+	/// Dispatch a call to the proxy pallet. Calls a proxy is allowed to make are dispatched here
+	/// signed by the real account, and their result only reported in an event.
+	fn dispatch_proxy(
+		&mut self,
+		origin: types::Origin,
+		call: proxy::Call<Self>,
+	) -> support::DispatchResult {
+		let who = origin.ensure_signed()?;
+		let (real, call) = match call {
+			proxy::Call::AddProxy { delegate, proxy_type, delay } => {
+				return self.proxy.add_proxy(&mut self.system, who, delegate, proxy_type, delay);
+			},
+			proxy::Call::RemoveProxy { delegate, proxy_type, delay } => {
+				return self.proxy.remove_proxy(&mut self.system, who, delegate, proxy_type, delay);
+			},
+			proxy::Call::Announce { real, call_hash } => {
+				return self.proxy.announce(&mut self.system, who, real, call_hash);
+			},
+			proxy::Call::RejectAnnouncement { delegate, call_hash } => {
+				return self.proxy.reject_announcement(&who, &delegate, call_hash);
+			},
+			proxy::Call::Proxy { real, call } => {
+				self.proxy.ensure_proxy(&who, &real, &call)?;
+				(real, call)
+			},
+			proxy::Call::ProxyAnnounced { delegate, real, call } => {
+				self.proxy.proxy_announced(&self.system, &delegate, &real, &call)?;
+				(real, call)
+			},
+		};
+		let result = self.dispatch(types::Origin::Signed(real), *call).map_err(String::from);
+		self.system.deposit_event(proxy::Event::ProxyExecuted { result });
+		Ok(())
+	}
+
+	/// Dispatch a call to the utility pallet, whose calls are dispatched here in turn with the
+	/// same origin.
+	fn dispatch_utility(
+		&mut self,
+		origin: types::Origin,
+		call: utility::Call<Self>,
+	) -> support::DispatchResult {
+		match call {
+			utility::Call::Batch { calls } => {
+				for (index, call) in calls.into_iter().enumerate() {
+					if let Err(error) = self.dispatch(origin.clone(), call) {
+						let error = error.to_string();
+						self.system
+							.deposit_event(utility::Event::BatchInterrupted { index, error });
+						return Ok(());
+					}
+				}
+			},
+			utility::Call::BatchAll { calls } => {
+				// Restoring the whole runtime from a snapshot undoes every change of the batch.
+				let snapshot = self.snapshot()?;
+				for (index, call) in calls.into_iter().enumerate() {
+					if let Err(error) = self.dispatch(origin.clone(), call) {
+						self.restore(&snapshot)?;
+						let event = utility::Event::BatchInterrupted { index, error: error.into() };
+						self.system.deposit_event(event);
+						return Err(error);
+					}
+				}
+			},
+			utility::Call::ForceBatch { calls } => {
+				let mut failed = false;
+				for (index, call) in calls.into_iter().enumerate() {
+					if let Err(error) = self.dispatch(origin.clone(), call) {
+						failed = true;
+						let error = error.to_string();
+						self.system.deposit_event(utility::Event::ItemFailed { index, error });
+					}
+				}
+				if failed {
+					self.system.deposit_event(utility::Event::BatchCompletedWithErrors);
+					return Ok(());
+				}
+			},
+		}
+		self.system.deposit_event(utility::Event::BatchCompleted);
 		Ok(())
 	}
+
+	/// Check the storage invariants of every pallet.
+	fn try_state(&self) -> support::DispatchResult {
+		self.balances.try_state()?;
+		Ok(())
+	}
+
+	/// Execute a block like `execute_block`, and return every storage change it made.
+	fn execute_block_with_diff(
+		&mut self,
+		block: types::Block,
+	) -> Result<state_diff::StateDiff, &'static str> {
+		let before = serde_json::to_value(&*self).map_err(|_| "failed to serialize state")?;
+		self.execute_block(block)?;
+		let after = serde_json::to_value(&*self).map_err(|_| "failed to serialize state")?;
+		Ok(state_diff::StateDiff::between(&before, &after))
+	}
+
+	/// Write the full runtime state, and the header of the last executed block, to `path`.
+	fn export_state(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		let header = types::Header { block_number: self.system.get_block_number() };
+		snapshot::export(path, &header, self)
+	}
+
+	/// Restore a runtime from a snapshot written by `export_state`.
+	fn import_state(path: impl AsRef<Path>) -> io::Result<Self> {
+		let snapshot::Snapshot { header, state } = snapshot::import::<types::Header, Self>(path)?;
+		if header.block_number != state.system.get_block_number() {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidData,
+				"snapshot header does not match the system block number",
+			));
+		}
+		Ok(state)
+	}
+}
+
+impl Default for Runtime {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl crate::support::Dispatch for Runtime {
-	type Caller = <Runtime as system::Config>::AccountId;
+	type Caller = types::Origin;
 	type Call = RuntimeCall;
 	fn dispatch(
 		&mut self,
@@ -77,27 +482,78 @@ impl crate::support::Dispatch for Runtime {
 		runtime_call: Self::Call,
 	) -> support::DispatchResult {
 		match runtime_call {
+			RuntimeCall::System(call) => {
+				self.system.dispatch(caller, call)?;
+			},
+			RuntimeCall::Timestamp(call) => {
+				self.timestamp.dispatch(caller, call)?;
+			},
 			// RuntimeCall::BalancesTransfer { to, amount } => {
 			// 	self.balances.transfer(&caller, &to, amount)?;
 			RuntimeCall::Balances(call) => {
-				self.balances.dispatch(caller, call)?;
+				self.balances.dispatch(&mut self.system, caller, call)?;
+			},
+			RuntimeCall::Assets(call) => {
+				self.assets.dispatch(&mut self.system, caller, call)?;
 			},
 			RuntimeCall::ProofOfExistence(call) => {
-				self.proof_of_existence.dispatch(caller, call)?;
+				let poe = &mut self.proof_of_existence;
+				let now = self.timestamp.now();
+				poe.dispatch(&mut self.system, &mut self.balances, now, caller, call)?;
+			},
+			RuntimeCall::Vesting(call) => {
+				self.vesting.dispatch(&mut self.balances, &mut self.system, caller, call)?;
+			},
+			RuntimeCall::Sudo(call) => {
+				self.dispatch_sudo(caller, call)?;
+			},
+			RuntimeCall::Multisig(call) => {
+				self.dispatch_multisig(caller, call)?;
+			},
+			RuntimeCall::Proxy(call) => {
+				self.dispatch_proxy(caller, call)?;
+			},
+			RuntimeCall::Utility(call) => {
+				self.dispatch_utility(caller, call)?;
 			},
 		}
 		Ok(())
 	}
 }
 
+/// Returns the value following `flag` on the command line, e.g. `--export-state state.json`.
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+	args.iter().position(|arg| arg == flag).and_then(|i| args.get(i + 1)).cloned()
+}
+
 fn main() {
+	let args: Vec<String> = std::env::args().collect();
+
+	// Resuming from a snapshot skips the demo blocks below, since they expect a fresh chain.
+	if let Some(path) = flag_value(&args, "--import-state") {
+		let runtime = Runtime::import_state(&path).expect("invalid state snapshot");
+		println!("{:#?}", runtime);
+		return;
+	}
+
 	let mut runtime = Runtime::new();
 
 	let alice = "alice".to_string();
 	let bob = "bob".to_string();
 	let charlie = "charlie".to_string();
 
-	runtime.balances.set_balance(&alice, 100);
+	runtime
+		.balances
+		.set_balance(&mut runtime.system, &alice, 100)
+		.expect("genesis balance overflows");
+	runtime.sudo.set_key(&mut runtime.system, alice.clone());
+	// Privileged calls like minting can only be dispatched with the `Root` origin.
+	runtime
+		.dispatch(
+			types::Origin::Root,
+			RuntimeCall::Balances(balances::Call::Mint { to: bob.clone(), amount: 50 }),
+		)
+		.expect("genesis mint failed");
 	// runtime.system.set_block_number();
 	// assert_eq!(runtime.system.get_block_number(), 1);
 
@@ -125,15 +581,38 @@ fn main() {
 		header: support::Header { block_number: 1 },
 		extrinsics: vec![
 			support::Extrinsic {
-				caller: alice.clone(),
+				caller: None,
+				call: RuntimeCall::Timestamp(timestamp::Call::Set { now: 1_700_000_000_000 }),
+			},
+			support::Extrinsic {
+				caller: Some(alice.clone()),
 				call: RuntimeCall::Balances(balances::Call::Transfer {
 					to: bob.clone(),
 					amount: 20,
 				}),
 			},
 			support::Extrinsic {
-				caller: alice.clone(),
-				call: RuntimeCall::Balances(balances::Call::Transfer { to: charlie, amount: 20 }),
+				caller: Some(alice.clone()),
+				call: RuntimeCall::Balances(balances::Call::Transfer {
+					to: charlie.clone(),
+					amount: 20,
+				}),
+			},
+			support::Extrinsic {
+				caller: Some(alice.clone()),
+				call: RuntimeCall::System(system::Call::RemarkWithEvent {
+					data: b"Paid bob and charlie".to_vec(),
+				}),
+			},
+			// As the sudo key, alice can top up charlie's balance.
+			support::Extrinsic {
+				caller: Some(alice.clone()),
+				call: RuntimeCall::Sudo(sudo::Call::Sudo {
+					call: Box::new(RuntimeCall::Balances(balances::Call::SetBalance {
+						who: charlie.clone(),
+						amount: 40,
+					})),
+				}),
 			},
 		],
 	};
@@ -142,41 +621,669 @@ fn main() {
 		header: support::Header { block_number: 2 },
 		extrinsics: vec![
 			support::Extrinsic {
-				caller: alice.clone(),
+				caller: None,
+				call: RuntimeCall::Timestamp(timestamp::Call::Set { now: 1_700_000_006_000 }),
+			},
+			support::Extrinsic {
+				caller: Some(alice.clone()),
 				call: RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
-					claim: &"Hello, world!",
+					claim: support::sha256(b"Hello, world!"),
+					memo: "A friendly greeting".to_string(),
+					expires_at: None,
 				}),
 			},
 			support::Extrinsic {
-				caller: bob.clone(),
+				caller: Some(bob.clone()),
 				call: RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
-					claim: &"Hello, world!",
+					claim: support::sha256(b"Hello, world!"),
+					memo: "A friendly greeting".to_string(),
+					expires_at: None,
 				}),
 			},
 		],
 	};
 
+	let invoices = [b"Invoice #2".as_slice(), b"Invoice #3".as_slice()];
+	let leaves: Vec<_> = invoices.iter().map(|invoice| support::sha256(invoice)).collect();
+	let invoice_tree = merkle::MerkleTree::new(&leaves, support::sha256).expect("no invoices");
+
 	let block_3 = types::Block {
 		header: support::Header { block_number: 3 },
 		extrinsics: vec![
 			support::Extrinsic {
-				caller: alice,
+				caller: None,
+				call: RuntimeCall::Timestamp(timestamp::Call::Set { now: 1_700_000_012_000 }),
+			},
+			support::Extrinsic {
+				caller: Some(alice.clone()),
 				call: RuntimeCall::ProofOfExistence(proof_of_existence::Call::RevokeClaim {
-					claim: &"Hello, world!",
+					claim: support::sha256(b"Hello, world!"),
 				}),
 			},
+			// One claim on the root of a Merkle tree covers every invoice in it.
 			support::Extrinsic {
-				caller: bob,
+				caller: Some(alice),
+				call: RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateBatchClaim {
+					root: *invoice_tree.root(),
+					memo: "Invoices #2 and #3".to_string(),
+					expires_at: None,
+				}),
+			},
+			support::Extrinsic {
+				caller: Some(bob.clone()),
 				call: RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
-					claim: &"Hello, world!",
+					claim: support::sha256(b"Hello, world!"),
+					memo: "A friendly greeting".to_string(),
+					expires_at: None,
+				}),
+			},
+			// Paying for a document and claiming it either both happen, or neither does.
+			support::Extrinsic {
+				caller: Some(bob.clone()),
+				call: RuntimeCall::Utility(utility::Call::BatchAll {
+					calls: vec![
+						RuntimeCall::Balances(balances::Call::Transfer { to: charlie, amount: 10 }),
+						RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
+							claim: support::sha256(b"Invoice #1"),
+							memo: "Paid to charlie".to_string(),
+							expires_at: None,
+						}),
+					],
 				}),
 			},
 		],
 	};
-	
-	runtime.execute_block(block_1).expect("invalid block");
-	runtime.execute_block(block_2).expect("invalid block");
-	runtime.execute_block(block_3).expect("invalid block");
-	
+
+	let print_diff = args.iter().any(|arg| arg == "--state-diff");
+	for block in [block_1, block_2, block_3] {
+		if print_diff {
+			let block_number = block.header.block_number;
+			let diff = runtime.execute_block_with_diff(block).expect("invalid block");
+			println!("State diff of block {}:\n{}", block_number, diff);
+		} else {
+			runtime.execute_block(block).expect("invalid block");
+		}
+	}
+
+	runtime.try_state().expect("storage invariants violated");
+	println!("Total issuance: {}", runtime.balances.total_issuance());
+
+	let (poe, system) = (&runtime.proof_of_existence, &runtime.system);
+	if let Some((_, claim)) = poe.verify_document(system, b"Hello, world!") {
+		println!("\"Hello, world!\" is claimed by {}", claim.owner);
+	}
+	let proof = invoice_tree.proof(1).expect("invoice #3 is in the tree");
+	let batch = poe.verify_batched_document(system, invoice_tree.root(), invoices[1], &proof);
+	println!("Invoice #3 is part of a claimed batch: {}", batch.is_some());
+	println!("Claims of bob: {}", poe.claims_of(&bob, 0, 10).len());
+	if let Some(path) = flag_value(&args, "--verify-file") {
+		let digest = proof_of_existence::Pallet::<Runtime>::hash_file(&path)
+			.expect("failed to read file to verify");
+		match poe.get_claim(system, &digest) {
+			Some(claim) => println!("{} is claimed by {}", path, claim.owner),
+			None => println!("{} is not claimed", path),
+		}
+	}
+
+	if let Some(path) = flag_value(&args, "--export-state") {
+		runtime.export_state(&path).expect("failed to export state snapshot");
+	}
+
 	println!("{:#?}", runtime);
 }
+
+#[cfg(test)]
+mod runtime_tests {
+	use super::{
+		balances, multisig, proof_of_existence, proxy, sudo, support, system, timestamp, types,
+		utility, Runtime, RuntimeCall, RuntimeEvent,
+	};
+	use crate::support::Dispatch;
+
+	/// A block which starts with the timestamp inherent for `now`.
+	fn block(
+		block_number: types::BlockNumber,
+		now: support::Moment,
+		extrinsics: Vec<types::Extrinsic>,
+	) -> types::Block {
+		let inherent = support::Extrinsic {
+			caller: None,
+			call: RuntimeCall::Timestamp(timestamp::Call::Set { now }),
+		};
+		let extrinsics = std::iter::once(inherent).chain(extrinsics).collect();
+		types::Block { header: support::Header { block_number }, extrinsics }
+	}
+
+	fn signed(who: &str, call: RuntimeCall) -> types::Extrinsic {
+		support::Extrinsic { caller: Some(who.to_string()), call }
+	}
+
+	fn funded_runtime() -> Runtime {
+		let mut runtime = Runtime::new();
+		for who in ["alice", "bob", "charlie"] {
+			runtime
+				.balances
+				.set_balance(&mut runtime.system, &who.to_string(), 100)
+				.unwrap();
+		}
+		runtime
+	}
+
+	#[test]
+	fn claims_record_the_block_timestamp() {
+		let claim = support::sha256(b"Hello, world!");
+		let create_claim = || {
+			block(
+				1,
+				1_700_000_000_000,
+				vec![signed(
+					"alice",
+					RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
+						claim,
+						memo: String::new(),
+						expires_at: None,
+					}),
+				)],
+			)
+		};
+
+		let mut runtime = funded_runtime();
+		assert_eq!(runtime.execute_block(create_claim()), Ok(()));
+		let info = runtime.proof_of_existence.get_claim(&runtime.system, &claim).unwrap();
+		assert_eq!((info.created_at_block, info.created_at_timestamp), (1, 1_700_000_000_000));
+
+		// The time comes from the block, so executing it again gives exactly the same state.
+		let mut replayed = funded_runtime();
+		assert_eq!(replayed.execute_block(create_claim()), Ok(()));
+		assert_eq!(
+			serde_json::to_value(&replayed).unwrap(),
+			serde_json::to_value(&runtime).unwrap()
+		);
+	}
+
+	#[test]
+	fn block_hash_covers_extrinsics() {
+		let remark = |data: &[u8]| {
+			signed("alice", RuntimeCall::System(system::Call::Remark { data: data.to_vec() }))
+		};
+		let block_hash = |extrinsics| {
+			let mut runtime = funded_runtime();
+			assert_eq!(runtime.execute_block(block(1, 10_000, extrinsics)), Ok(()));
+			runtime.system.block_hash(1).unwrap()
+		};
+
+		let hello = block_hash(vec![remark(b"hello")]);
+		assert_eq!(block_hash(vec![remark(b"hello")]), hello);
+		assert_ne!(block_hash(vec![remark(b"goodbye")]), hello);
+		assert_ne!(block_hash(vec![]), hello);
+		// The same call from another signer is a different block too.
+		let from_bob =
+			signed("bob", RuntimeCall::System(system::Call::Remark { data: b"hello".to_vec() }));
+		assert_ne!(block_hash(vec![from_bob]), hello);
+	}
+
+	#[test]
+	fn invalid_blocks_leave_state_untouched() {
+		let mut runtime = funded_runtime();
+		let transfer = || {
+			signed(
+				"alice",
+				RuntimeCall::Balances(balances::Call::Transfer {
+					to: "bob".to_string(),
+					amount: 10,
+				}),
+			)
+		};
+		let state = |runtime: &Runtime| serde_json::to_value(runtime).unwrap();
+		let genesis = state(&runtime);
+
+		assert_eq!(
+			runtime.execute_block(block(2, 1_000, vec![transfer()])),
+			Err("Wrong block number")
+		);
+		assert_eq!(state(&runtime), genesis);
+
+		let no_inherent = types::Block {
+			header: support::Header { block_number: 1 },
+			extrinsics: vec![transfer()],
+		};
+		assert_eq!(
+			runtime.execute_block(no_inherent),
+			Err("block must start with exactly one timestamp inherent")
+		);
+		assert_eq!(state(&runtime), genesis);
+
+		assert_eq!(runtime.execute_block(block(1, 10_000, vec![transfer()])), Ok(()));
+		let after_block_1 = state(&runtime);
+
+		// This inherent is too soon after the previous timestamp, so the block is rejected after the
+		// block number and events have been touched, and those changes are undone.
+		assert_eq!(
+			runtime.execute_block(block(2, 11_000, vec![transfer()])),
+			Err("timestamp must increment by at least the minimum period")
+		);
+		assert_eq!(state(&runtime), after_block_1);
+		assert_eq!(runtime.system.get_block_number(), 1);
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 110);
+	}
+
+	#[test]
+	fn deposits_keep_accounts_alive() {
+		let mut runtime = funded_runtime();
+		let alice = types::Origin::Signed("alice".to_string());
+		let hello = support::sha256(b"Hello, world!");
+		let transfer = |amount| {
+			RuntimeCall::Balances(balances::Call::Transfer { to: "bob".to_string(), amount })
+		};
+		let claim = RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
+			claim: hello,
+			memo: String::new(),
+			expires_at: None,
+		});
+		let revoke =
+			RuntimeCall::ProofOfExistence(proof_of_existence::Call::RevokeClaim { claim: hello });
+
+		// Sending everything but the claim deposit would leave alice below the existential
+		// deposit, but the account cannot be reaped while the claim holds a deposit from it.
+		assert_eq!(runtime.dispatch(alice.clone(), claim), Ok(()));
+		assert_eq!(
+			runtime.dispatch(alice.clone(), transfer(95)),
+			Err("account is still in use by other pallets")
+		);
+		assert_eq!(runtime.dispatch(alice.clone(), revoke), Ok(()));
+		assert_eq!(runtime.dispatch(alice, transfer(100)), Ok(()));
+		assert!(!runtime.system.account_exists(&"alice".to_string()));
+		assert_eq!(runtime.try_state(), Ok(()));
+	}
+
+	#[test]
+	fn sudo_dispatches_as_root_or_as_another_account() {
+		let mut runtime = funded_runtime();
+		runtime.sudo.set_key(&mut runtime.system, "alice".to_string());
+		let signed_by = |who: &str| types::Origin::Signed(who.to_string());
+		let sudo = |call| RuntimeCall::Sudo(sudo::Call::Sudo { call: Box::new(call) });
+		let sudo_as = |who: &str, call| {
+			RuntimeCall::Sudo(sudo::Call::SudoAs { who: who.to_string(), call: Box::new(call) })
+		};
+		let set_balance = |amount| {
+			RuntimeCall::Balances(balances::Call::SetBalance { who: "charlie".to_string(), amount })
+		};
+		let transfer = |amount| {
+			RuntimeCall::Balances(balances::Call::Transfer { to: "charlie".to_string(), amount })
+		};
+		let last_event = |runtime: &Runtime| runtime.system.events().last().cloned();
+
+		// The key dispatches root calls, and only the key.
+		assert_eq!(runtime.dispatch(signed_by("alice"), sudo(set_balance(500))), Ok(()));
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), 500);
+		assert_eq!(
+			last_event(&runtime),
+			Some(RuntimeEvent::Sudo(sudo::Event::Sudid { result: Ok(()) }))
+		);
+		assert_eq!(
+			runtime.dispatch(signed_by("bob"), sudo(set_balance(0))),
+			Err("sender must be the sudo key")
+		);
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), 500);
+
+		// A failing inner call is reported in the event, and the sudo call itself succeeds.
+		assert_eq!(runtime.dispatch(signed_by("alice"), sudo(transfer(10))), Ok(()));
+		assert_eq!(
+			last_event(&runtime),
+			Some(RuntimeEvent::Sudo(sudo::Event::Sudid { result: Err("bad origin".to_string()) }))
+		);
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), 500);
+
+		// `SudoAs` signs the inner call with `who`, not with the key.
+		assert_eq!(runtime.dispatch(signed_by("alice"), sudo_as("bob", transfer(10))), Ok(()));
+		assert_eq!(
+			last_event(&runtime),
+			Some(RuntimeEvent::Sudo(sudo::Event::SudoAsDone { result: Ok(()) }))
+		);
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 100);
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 90);
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), 510);
+		assert_eq!(
+			runtime.dispatch(signed_by("bob"), sudo_as("alice", transfer(10))),
+			Err("sender must be the sudo key")
+		);
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 100);
+	}
+
+	#[test]
+	fn multisig_dispatches_once_the_threshold_is_met() {
+		let mut runtime = funded_runtime();
+		let [alice, bob, charlie] = ["alice", "bob", "charlie"].map(String::from);
+		let multisig_account = multisig::Pallet::<Runtime>::multi_account_id(
+			&[alice.clone(), bob.clone(), charlie.clone()],
+			2,
+		);
+		runtime
+			.balances
+			.set_balance(&mut runtime.system, &multisig_account, 50)
+			.unwrap();
+
+		let transfer = || {
+			RuntimeCall::Balances(balances::Call::Transfer {
+				to: "charlie".to_string(),
+				amount: 20,
+			})
+		};
+		let call_hash = support::call_hash(&transfer()).unwrap();
+		let as_multi = |other_signatories: [&String; 2]| {
+			RuntimeCall::Multisig(multisig::Call::AsMulti {
+				threshold: 2,
+				other_signatories: other_signatories.map(String::clone).to_vec(),
+				call: Box::new(transfer()),
+			})
+		};
+
+		// The first approval only holds a deposit from alice.
+		assert_eq!(
+			runtime.dispatch(types::Origin::Signed(alice.clone()), as_multi([&bob, &charlie])),
+			Ok(())
+		);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 5);
+		assert_eq!(runtime.balances.balance(&multisig_account), 50);
+
+		// The second one meets the threshold, so the transfer is made from the multisig account.
+		assert_eq!(
+			runtime.dispatch(types::Origin::Signed(bob.clone()), as_multi([&alice, &charlie])),
+			Ok(())
+		);
+		assert_eq!(runtime.balances.balance(&multisig_account), 30);
+		assert_eq!(runtime.balances.balance(&charlie), 120);
+		assert_eq!(runtime.balances.reserved_balance(&alice), 0);
+		assert_eq!(runtime.balances.balance(&alice), 100);
+		assert_eq!(
+			runtime.system.events().last(),
+			Some(&RuntimeEvent::Multisig(multisig::Event::MultisigExecuted {
+				approving: bob,
+				multisig: multisig_account,
+				call_hash,
+				result: Ok(()),
+			}))
+		);
+		assert_eq!(runtime.try_state(), Ok(()));
+	}
+
+	#[test]
+	fn transfer_proxies_only_move_funds() {
+		let mut runtime = funded_runtime();
+		let signed_by = |who: &str| types::Origin::Signed(who.to_string());
+		let transfer = || {
+			RuntimeCall::Balances(balances::Call::Transfer {
+				to: "charlie".to_string(),
+				amount: 10,
+			})
+		};
+		let proxy = |call| {
+			RuntimeCall::Proxy(proxy::Call::Proxy {
+				real: "alice".to_string(),
+				call: Box::new(call),
+			})
+		};
+		let add_proxy = RuntimeCall::Proxy(proxy::Call::AddProxy {
+			delegate: "bob".to_string(),
+			proxy_type: proxy::ProxyType::Transfer,
+			delay: 0,
+		});
+		assert_eq!(runtime.dispatch(signed_by("alice"), add_proxy), Ok(()));
+
+		assert_eq!(runtime.dispatch(signed_by("bob"), proxy(transfer())), Ok(()));
+		assert_eq!(
+			runtime.system.events().last(),
+			Some(&RuntimeEvent::Proxy(proxy::Event::ProxyExecuted { result: Ok(()) }))
+		);
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 90);
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 100);
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), 110);
+
+		// Neither another pallet's call nor a batch wrapping a transfer gets through the filter.
+		let claim = RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
+			claim: support::sha256(b"Hello, world!"),
+			memo: String::new(),
+			expires_at: None,
+		});
+		let batch = RuntimeCall::Utility(utility::Call::Batch { calls: vec![transfer()] });
+		for call in [claim, batch] {
+			assert_eq!(
+				runtime.dispatch(signed_by("bob"), proxy(call)),
+				Err("call is not allowed for this proxy type")
+			);
+		}
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 90);
+		assert!(runtime
+			.proof_of_existence
+			.get_claim(&runtime.system, &support::sha256(b"Hello, world!"))
+			.is_none());
+	}
+
+	#[test]
+	fn delayed_proxies_dispatch_announced_calls() {
+		let mut runtime = funded_runtime();
+		let signed_by = |who: &str| types::Origin::Signed(who.to_string());
+		let transfer = || {
+			RuntimeCall::Balances(balances::Call::Transfer {
+				to: "charlie".to_string(),
+				amount: 10,
+			})
+		};
+		let proxy_announced = || {
+			RuntimeCall::Proxy(proxy::Call::ProxyAnnounced {
+				delegate: "bob".to_string(),
+				real: "alice".to_string(),
+				call: Box::new(transfer()),
+			})
+		};
+		let add_proxy = RuntimeCall::Proxy(proxy::Call::AddProxy {
+			delegate: "bob".to_string(),
+			proxy_type: proxy::ProxyType::Transfer,
+			delay: 2,
+		});
+		let announce = RuntimeCall::Proxy(proxy::Call::Announce {
+			real: "alice".to_string(),
+			call_hash: support::call_hash(&transfer()).unwrap(),
+		});
+		assert_eq!(runtime.dispatch(signed_by("alice"), add_proxy), Ok(()));
+		assert_eq!(runtime.dispatch(signed_by("bob"), announce), Ok(()));
+
+		runtime.system.set_block_number();
+		assert_eq!(
+			runtime.dispatch(signed_by("charlie"), proxy_announced()),
+			Err("announcement is not yet due")
+		);
+		runtime.system.set_block_number();
+		// Anyone can dispatch the call once it is due, and it is signed by the real account.
+		assert_eq!(runtime.dispatch(signed_by("charlie"), proxy_announced()), Ok(()));
+		assert_eq!(
+			runtime.system.events().last(),
+			Some(&RuntimeEvent::Proxy(proxy::Event::ProxyExecuted { result: Ok(()) }))
+		);
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 90);
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), 110);
+
+		// The announcement is used up, so the call cannot be replayed.
+		assert_eq!(
+			runtime.dispatch(signed_by("charlie"), proxy_announced()),
+			Err("announcement not found")
+		);
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 90);
+	}
+
+	fn transfer(to: &str, amount: types::Balance) -> RuntimeCall {
+		RuntimeCall::Balances(balances::Call::Transfer { to: to.to_string(), amount })
+	}
+
+	fn utility_events(runtime: &Runtime) -> Vec<utility::Event> {
+		let events = runtime.system.events().iter();
+		events
+			.filter_map(|event| match event {
+				RuntimeEvent::Utility(event) => Some(event.clone()),
+				_ => None,
+			})
+			.collect()
+	}
+
+	#[test]
+	fn batch_stops_at_the_first_failure() {
+		let mut runtime = funded_runtime();
+		let alice = types::Origin::Signed("alice".to_string());
+		let batch = RuntimeCall::Utility(utility::Call::Batch {
+			calls: vec![transfer("bob", 10), transfer("bob", 1_000), transfer("charlie", 10)],
+		});
+
+		assert_eq!(runtime.dispatch(alice, batch), Ok(()));
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 110);
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), 100);
+		assert_eq!(
+			utility_events(&runtime),
+			[utility::Event::BatchInterrupted {
+				index: 1,
+				error: "Not enough balance".to_string()
+			}]
+		);
+	}
+
+	#[test]
+	fn batch_all_rolls_back_every_call() {
+		let mut runtime = funded_runtime();
+		let alice = types::Origin::Signed("alice".to_string());
+		let batch_all = |calls| RuntimeCall::Utility(utility::Call::BatchAll { calls });
+		let remark = RuntimeCall::System(system::Call::RemarkWithEvent { data: b"hi".to_vec() });
+		assert_eq!(runtime.dispatch(alice.clone(), remark), Ok(()));
+		let events_before = runtime.system.events().to_vec();
+		let state = |runtime: &Runtime| serde_json::to_value(runtime).unwrap();
+		let before = state(&runtime);
+
+		// The failing call is nested in a second `batch_all`, and the outer one undoes the
+		// transfers of both.
+		let nested = batch_all(vec![transfer("charlie", 10), transfer("charlie", 1_000)]);
+		assert_eq!(
+			runtime.dispatch(alice.clone(), batch_all(vec![transfer("bob", 10), nested])),
+			Err("Not enough balance")
+		);
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 100);
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 100);
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), 100);
+
+		// The events from before the batch are kept, the events of the undone calls are not, and
+		// only the outer batch reports where it failed.
+		let event =
+			utility::Event::BatchInterrupted { index: 1, error: "Not enough balance".to_string() };
+		let mut events = events_before;
+		events.push(RuntimeEvent::Utility(event));
+		assert_eq!(runtime.system.events(), events);
+		let mut after = state(&runtime);
+		after["system"]["events"] = before["system"]["events"].clone();
+		assert_eq!(after, before);
+		runtime.system.reset_events();
+
+		assert_eq!(runtime.dispatch(alice, batch_all(vec![transfer("bob", 10)])), Ok(()));
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 110);
+		assert_eq!(utility_events(&runtime), [utility::Event::BatchCompleted]);
+	}
+
+	#[test]
+	fn force_batch_reports_every_failure() {
+		let mut runtime = funded_runtime();
+		let alice = types::Origin::Signed("alice".to_string());
+		let force_batch = RuntimeCall::Utility(utility::Call::ForceBatch {
+			calls: vec![
+				transfer("bob", 1_000),
+				transfer("bob", 10),
+				transfer("charlie", 1_000),
+				transfer("charlie", 10),
+			],
+		});
+
+		assert_eq!(runtime.dispatch(alice, force_batch), Ok(()));
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 110);
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), 110);
+		let failed =
+			|index| utility::Event::ItemFailed { index, error: "Not enough balance".to_string() };
+		assert_eq!(
+			utility_events(&runtime),
+			[failed(0), failed(2), utility::Event::BatchCompletedWithErrors]
+		);
+	}
+
+	#[test]
+	fn state_snapshots_round_trip() {
+		let mut runtime = funded_runtime();
+		let claim = RuntimeCall::ProofOfExistence(proof_of_existence::Call::CreateClaim {
+			claim: support::sha256(b"Hello, world!"),
+			memo: "memo".to_string(),
+			expires_at: Some(10),
+		});
+		let extrinsics = vec![signed("alice", claim), signed("bob", transfer("charlie", 10))];
+		assert_eq!(runtime.execute_block(block(1, 10_000, extrinsics)), Ok(()));
+
+		let path = std::env::temp_dir().join("rust-state-machine-runtime-snapshot-test.json");
+		runtime.export_state(&path).unwrap();
+		let mut imported = Runtime::import_state(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		assert_eq!(
+			serde_json::to_value(&imported).unwrap(),
+			serde_json::to_value(&runtime).unwrap()
+		);
+
+		// The imported runtime carries on from the same state, to the same block hash.
+		let next = || block(2, 20_000, vec![signed("charlie", transfer("alice", 5))]);
+		assert_eq!(runtime.execute_block(next()), Ok(()));
+		assert_eq!(imported.execute_block(next()), Ok(()));
+		assert_eq!(imported.system.block_hash(2), runtime.system.block_hash(2));
+		assert!(imported.system.block_hash(2).is_some());
+	}
+
+	#[test]
+	fn balances_above_u64_survive_snapshots() {
+		let mut runtime = funded_runtime();
+		runtime.sudo.set_key(&mut runtime.system, "alice".to_string());
+		let huge = types::Balance::from(u64::MAX) + 1;
+		let mint = RuntimeCall::Sudo(sudo::Call::Sudo {
+			call: Box::new(RuntimeCall::Balances(balances::Call::Mint {
+				to: "bob".to_string(),
+				amount: huge,
+			})),
+		});
+		let diff = runtime.execute_block_with_diff(block(1, 10_000, vec![signed("alice", mint)]));
+		// The diff shows the exact balance, not a float rounded to fit in 64 bits.
+		assert!(diff.unwrap().to_string().contains(&(huge + 100).to_string()));
+
+		// Later blocks, and the rollback of an invalid one, still work with the huge balance.
+		let to_charlie = || signed("bob", transfer("charlie", huge));
+		assert_eq!(runtime.execute_block(block(2, 20_000, vec![to_charlie()])), Ok(()));
+		assert_eq!(
+			runtime.execute_block(block(3, 21_000, vec![to_charlie()])),
+			Err("timestamp must increment by at least the minimum period")
+		);
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), huge + 100);
+
+		let batch_all = RuntimeCall::Utility(utility::Call::BatchAll {
+			calls: vec![transfer("alice", huge), transfer("alice", huge)],
+		});
+		assert_eq!(
+			runtime.dispatch(types::Origin::Signed("charlie".to_string()), batch_all),
+			Err("Not enough balance")
+		);
+		assert_eq!(runtime.balances.balance(&"charlie".to_string()), huge + 100);
+		assert_eq!(runtime.try_state(), Ok(()));
+	}
+
+	#[test]
+	fn extrinsics_from_unknown_accounts_are_rejected() {
+		let mut runtime = funded_runtime();
+		let remark = || RuntimeCall::System(system::Call::Remark { data: b"hello".to_vec() });
+		let extrinsics = vec![signed("nobody", remark()), signed("alice", remark())];
+
+		assert_eq!(runtime.execute_block(block(1, 10_000, extrinsics)), Ok(()));
+		assert_eq!(
+			runtime.system.account(&"nobody".to_string()),
+			system::AccountInfo { nonce: 0, providers: 0, consumers: 0, sufficients: 0 }
+		);
+		let state = serde_json::to_value(&runtime).unwrap();
+		assert!(state["system"]["account"].get("nobody").is_none());
+		assert_eq!(runtime.system.nonce(&"alice".to_string()), 1);
+	}
+}
@@ -0,0 +1,112 @@
+//! Merkle trees over document digests, so that a single claim on the root of a tree proves the
+//! existence of every document in it.
+//!
+//! Leaves and inner nodes are hashed with different prefixes, so an inner node can never be passed
+//! off as a leaf. The two children of a node are sorted before hashing, which means a proof is just
+//! the list of siblings on the path to the root. A node without a sibling is carried up to the next
+//! layer unchanged.
+
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+#[derive(Debug, Clone)]
+pub struct MerkleTree<H> {
+	/// Every layer of the tree, from the hashed leaves up to the root.
+	layers: Vec<Vec<H>>,
+}
+
+impl<H: AsRef<[u8]> + Ord + Clone> MerkleTree<H> {
+	/// Build the tree over `leaves`, or return `None` if there are no leaves.
+	pub fn new(leaves: &[H], hash: impl Fn(&[u8]) -> H) -> Option<Self> {
+		if leaves.is_empty() {
+			return None;
+		}
+		let mut layers = vec![leaves.iter().map(|leaf| hash_leaf(leaf, &hash)).collect::<Vec<_>>()];
+		while let Some(layer) = layers.last().filter(|layer| layer.len() > 1) {
+			let next = layer
+				.chunks(2)
+				.map(|pair| match pair {
+					[left, right] => hash_node(left, right, &hash),
+					[single] => single.clone(),
+					_ => unreachable!("chunks are one or two nodes"),
+				})
+				.collect();
+			layers.push(next);
+		}
+		Some(Self { layers })
+	}
+
+	pub fn root(&self) -> &H {
+		&self.layers[self.layers.len() - 1][0]
+	}
+
+	/// The proof that the leaf at `index` is part of this tree, to be checked with
+	/// `verify_inclusion`.
+	pub fn proof(&self, mut index: usize) -> Option<Vec<H>> {
+		if index >= self.layers[0].len() {
+			return None;
+		}
+		let mut proof = Vec::new();
+		for layer in &self.layers[..self.layers.len() - 1] {
+			if let Some(sibling) = layer.get(index ^ 1) {
+				proof.push(sibling.clone());
+			}
+			index /= 2;
+		}
+		Some(proof)
+	}
+}
+
+/// Check that `leaf` is part of the tree with the given `root`, using a proof from
+/// `MerkleTree::proof`.
+pub fn verify_inclusion<H: AsRef<[u8]> + Ord + Clone>(
+	root: &H,
+	leaf: &H,
+	proof: &[H],
+	hash: impl Fn(&[u8]) -> H,
+) -> bool {
+	let computed = proof
+		.iter()
+		.fold(hash_leaf(leaf, &hash), |node, sibling| hash_node(&node, sibling, &hash));
+	computed == *root
+}
+
+fn hash_leaf<H: AsRef<[u8]>>(leaf: &H, hash: impl Fn(&[u8]) -> H) -> H {
+	hash(&[&[LEAF_PREFIX], leaf.as_ref()].concat())
+}
+
+fn hash_node<H: AsRef<[u8]> + Ord>(a: &H, b: &H, hash: impl Fn(&[u8]) -> H) -> H {
+	let (low, high) = if a <= b { (a, b) } else { (b, a) };
+	hash(&[&[NODE_PREFIX], low.as_ref(), high.as_ref()].concat())
+}
+
+#[cfg(test)]
+mod merkle_tests {
+	use super::{verify_inclusion, MerkleTree};
+	use crate::support::sha256;
+
+	#[test]
+	fn build_and_verify_proofs() {
+		assert!(MerkleTree::new(&[], sha256).is_none());
+
+		for count in 1..=7u8 {
+			let leaves: Vec<_> = (0..count).map(|i| sha256(&[i])).collect();
+			let tree = MerkleTree::new(&leaves, sha256).unwrap();
+			for (i, leaf) in leaves.iter().enumerate() {
+				let proof = tree.proof(i).unwrap();
+				assert!(verify_inclusion(tree.root(), leaf, &proof, sha256));
+			}
+			assert_eq!(tree.proof(leaves.len()), None);
+		}
+
+		let leaves: Vec<_> = (0..5u8).map(|i| sha256(&[i])).collect();
+		let tree = MerkleTree::new(&leaves, sha256).unwrap();
+		let proof = tree.proof(2).unwrap();
+		// Proofs only hold for the leaf they were made for, and only for their own tree.
+		assert!(!verify_inclusion(tree.root(), &leaves[3], &proof, sha256));
+		assert!(!verify_inclusion(tree.root(), &sha256(b"not a leaf"), &proof, sha256));
+		assert!(!verify_inclusion(&leaves[0], &leaves[2], &proof, sha256));
+		// Inner nodes cannot be passed off as leaves.
+		assert!(!verify_inclusion(tree.root(), &proof[0], &proof[1..], sha256));
+	}
+}
@@ -0,0 +1,414 @@
+use crate::{
+	support::{Currency, Hash, ReservableCurrency},
+	system,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub trait Config: system::Config<RuntimeEvent: From<Event<Self::AccountId>>> + Sized {
+	/// The calls of every pallet in the runtime, which a multisig account can dispatch.
+	type RuntimeCall: Serialize;
+	/// The currency deposits for pending multisig operations are taken in.
+	type Currency: ReservableCurrency<Self>;
+	/// The amount reserved from the first approver of an operation until it is executed or
+	/// cancelled.
+	const MULTISIG_DEPOSIT: BalanceOf<Self>;
+	/// The most signatories a multisig account can have.
+	const MAX_SIGNATORIES: usize;
+
+	/// The account controlled by `signatories`, which are sorted, once `threshold` of them
+	/// approve. The same signatories and threshold must always give the same account.
+	fn multi_account_id(signatories: &[Self::AccountId], threshold: u16) -> Self::AccountId;
+}
+
+pub type BalanceOf<T> = <<T as Config>::Currency as Currency<T>>::Balance;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Event<AccountId> {
+	/// `approving` started a new operation for `multisig`.
+	NewMultisig { approving: AccountId, multisig: AccountId, call_hash: Hash },
+	/// `approving` approved a pending operation of `multisig`.
+	MultisigApproval { approving: AccountId, multisig: AccountId, call_hash: Hash },
+	/// An operation reached its threshold and was dispatched from `multisig`.
+	MultisigExecuted {
+		approving: AccountId,
+		multisig: AccountId,
+		call_hash: Hash,
+		result: Result<(), String>,
+	},
+	/// The depositor of a pending operation cancelled it.
+	MultisigCancelled { cancelling: AccountId, multisig: AccountId, call_hash: Hash },
+}
+
+/// A pending operation of a multisig account: the approvals for a call so far, and the deposit
+/// held for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Multisig<AccountId, BlockNumber, Balance> {
+	/// The block the first approval was made in.
+	pub when: BlockNumber,
+	pub depositor: AccountId,
+	pub deposit: Balance,
+	/// The signatories who approved, sorted.
+	pub approvals: Vec<AccountId>,
+}
+
+/// Accounts controlled by several signatories, which dispatch a call once enough of them have
+/// approved it.
+///
+/// The call of an operation is only known by its hash until `as_multi` provides it, and the
+/// runtime then dispatches it from the multisig account.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "T::AccountId: Serialize + DeserializeOwned, T::BlockNumber: Serialize + \
+	DeserializeOwned, BalanceOf<T>: Serialize + DeserializeOwned")]
+pub struct Pallet<T: Config> {
+	/// Pending operations, keyed by the multisig account and the hash of the call.
+	#[serde(with = "crate::support::storage_map")]
+	#[allow(clippy::type_complexity)]
+	multisigs: BTreeMap<
+		(T::AccountId, Hash),
+		Multisig<T::AccountId, T::BlockNumber, <T::Currency as Currency<T>>::Balance>,
+	>,
+}
+
+impl<T: Config> Pallet<T> {
+	pub fn new() -> Self {
+		Self { multisigs: BTreeMap::new() }
+	}
+
+	/// The account controlled by `signatories`, in any order, with the given `threshold`.
+	#[cfg(test)]
+	pub fn multi_account_id(signatories: &[T::AccountId], threshold: u16) -> T::AccountId {
+		let mut signatories = signatories.to_vec();
+		signatories.sort();
+		T::multi_account_id(&signatories, threshold)
+	}
+
+	#[cfg(test)]
+	#[allow(clippy::type_complexity)]
+	pub fn multisig(
+		&self,
+		multisig: &T::AccountId,
+		call_hash: &Hash,
+	) -> Option<Multisig<T::AccountId, T::BlockNumber, BalanceOf<T>>> {
+		self.multisigs.get(&(multisig.clone(), *call_hash)).cloned()
+	}
+
+	/// Approve the call with `call_hash` from the multisig account of `who` and
+	/// `other_signatories`. The first approval reserves `Config::MULTISIG_DEPOSIT` from `who`.
+	/// The call is only dispatched by `as_multi`, even once the threshold is met.
+	pub fn approve_as_multi(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		currency: &mut T::Currency,
+		who: T::AccountId,
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		call_hash: Hash,
+	) -> Result<(), &'static str> {
+		let multisig = Self::ensure_signatories(&who, threshold, other_signatories)?;
+		self.approve(system, currency, who, multisig, call_hash)?;
+		Ok(())
+	}
+
+	/// Like `approve_as_multi`, but with the call itself. If this approval meets the threshold,
+	/// the operation is removed and its deposit returned, and the multisig account is returned
+	/// for the runtime to dispatch the call from.
+	pub fn as_multi(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		currency: &mut T::Currency,
+		who: T::AccountId,
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		call_hash: Hash,
+	) -> Result<Option<T::AccountId>, &'static str> {
+		let multisig = Self::ensure_signatories(&who, threshold, other_signatories)?;
+		let key = (multisig.clone(), call_hash);
+		// A signatory who already approved can still provide the call once the threshold is met.
+		let already_approved =
+			self.multisigs.get(&key).is_some_and(|pending| pending.approvals.contains(&who));
+		let approvals = if already_approved {
+			self.multisigs[&key].approvals.len()
+		} else {
+			self.approve(system, currency, who, multisig.clone(), call_hash)?
+		};
+		if approvals < usize::from(threshold) {
+			return if already_approved { Err("already approved") } else { Ok(None) };
+		}
+
+		if let Some(pending) = self.multisigs.remove(&key) {
+			currency.unreserve(&pending.depositor, pending.deposit);
+			system.dec_consumers(&pending.depositor);
+		}
+		Ok(Some(multisig))
+	}
+
+	/// Cancel a pending operation, returning its deposit. Only the signatory who made the first
+	/// approval, and so paid the deposit, can cancel it.
+	pub fn cancel_as_multi(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		currency: &mut T::Currency,
+		who: T::AccountId,
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		call_hash: Hash,
+	) -> Result<(), &'static str> {
+		let multisig = Self::ensure_signatories(&who, threshold, other_signatories)?;
+		let key = (multisig.clone(), call_hash);
+		let pending = self.multisigs.get(&key).ok_or("multisig operation not found")?;
+		if pending.depositor != who {
+			return Err("only the depositor can cancel");
+		}
+
+		currency.unreserve(&pending.depositor, pending.deposit);
+		system.dec_consumers(&pending.depositor);
+		self.multisigs.remove(&key);
+		system.deposit_event(Event::MultisigCancelled { cancelling: who, multisig, call_hash });
+		Ok(())
+	}
+
+	/// Check `who` and `other_signatories` form a valid set of signatories for `threshold`, and
+	/// return their multisig account.
+	fn ensure_signatories(
+		who: &T::AccountId,
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+	) -> Result<T::AccountId, &'static str> {
+		let mut signatories = other_signatories;
+		signatories.push(who.clone());
+		signatories.sort();
+		let count = signatories.len();
+		signatories.dedup();
+		if signatories.len() != count {
+			return Err("signatories must be unique");
+		}
+		if count > T::MAX_SIGNATORIES {
+			return Err("too many signatories");
+		}
+		if threshold < 2 || usize::from(threshold) > count {
+			return Err("threshold must be at least two, and at most the number of signatories");
+		}
+		Ok(T::multi_account_id(&signatories, threshold))
+	}
+
+	/// Record the approval of `who` for the call with `call_hash`, starting a new operation if
+	/// there is none, and return how many approvals the operation has.
+	fn approve(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		currency: &mut T::Currency,
+		who: T::AccountId,
+		multisig: T::AccountId,
+		call_hash: Hash,
+	) -> Result<usize, &'static str> {
+		let key = (multisig.clone(), call_hash);
+		let Some(pending) = self.multisigs.get_mut(&key) else {
+			// The depositor cannot be reaped while the operation, and its deposit, are pending.
+			system.inc_consumers(&who)?;
+			if let Err(error) = currency.reserve(&who, T::MULTISIG_DEPOSIT) {
+				system.dec_consumers(&who);
+				return Err(error);
+			}
+			self.multisigs.insert(
+				key,
+				Multisig {
+					when: system.get_block_number(),
+					depositor: who.clone(),
+					deposit: T::MULTISIG_DEPOSIT,
+					approvals: vec![who.clone()],
+				},
+			);
+			system.deposit_event(Event::NewMultisig { approving: who, multisig, call_hash });
+			return Ok(1);
+		};
+
+		let Err(index) = pending.approvals.binary_search(&who) else {
+			return Err("already approved");
+		};
+		pending.approvals.insert(index, who.clone());
+		let approvals = pending.approvals.len();
+		system.deposit_event(Event::MultisigApproval { approving: who, multisig, call_hash });
+		Ok(approvals)
+	}
+}
+
+// The variants mirror the names of the functions they call.
+#[allow(clippy::enum_variant_names)]
+#[derive(Serialize)]
+#[serde(bound = "T::AccountId: Serialize")]
+pub enum Call<T: Config> {
+	/// Approve `call` from the multisig account of the caller and `other_signatories`, and
+	/// dispatch it if this meets the threshold.
+	AsMulti {
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		call: Box<T::RuntimeCall>,
+	},
+	/// Approve the call with `call_hash` without providing the call itself.
+	ApproveAsMulti {
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		call_hash: Hash,
+	},
+	CancelAsMulti {
+		threshold: u16,
+		other_signatories: Vec<T::AccountId>,
+		call_hash: Hash,
+	},
+}
+
+#[cfg(test)]
+mod multisig_tests {
+	use super::{Event, Pallet};
+	use crate::{
+		balances,
+		support::{call_hash, sha256},
+		system,
+	};
+
+	crate::support::test_runtime!(String, Multisig(Event<String>));
+	impl balances::Config for TestConfig {
+		type Balance = u128;
+		const EXISTENTIAL_DEPOSIT: u128 = 1;
+	}
+	impl super::Config for TestConfig {
+		type RuntimeCall = String;
+		type Currency = balances::Pallet<Self>;
+		const MULTISIG_DEPOSIT: u128 = 10;
+		const MAX_SIGNATORIES: usize = 3;
+
+		fn multi_account_id(signatories: &[String], threshold: u16) -> String {
+			format!("{}/{}", signatories.join("+"), threshold)
+		}
+	}
+
+	#[test]
+	fn multisig_account_id_is_deterministic() {
+		let signatories = |names: [&str; 3]| names.map(String::from);
+		let id = Pallet::<TestConfig>::multi_account_id;
+		assert_eq!(id(&signatories(["bob", "alice", "charlie"]), 2), "alice+bob+charlie/2");
+		assert_eq!(id(&signatories(["charlie", "bob", "alice"]), 2), "alice+bob+charlie/2");
+		assert_ne!(id(&signatories(["alice", "bob", "charlie"]), 3), "alice+bob+charlie/2");
+	}
+
+	#[test]
+	fn threshold_approvals() {
+		let mut system = system::Pallet::<TestConfig>::new();
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		let mut multisig = Pallet::<TestConfig>::new();
+
+		let [alice, bob, charlie, dave] = ["alice", "bob", "charlie", "dave"].map(String::from);
+		for who in [&alice, &bob] {
+			assert_eq!(balances.set_balance(&mut system, who, 100), Ok(()));
+		}
+		let others = |who: &String| {
+			[&alice, &bob, &charlie]
+				.into_iter()
+				.filter(|other| *other != who)
+				.cloned()
+				.collect()
+		};
+		let multi = "alice+bob+charlie/2".to_string();
+		let call_hash = call_hash(&"pay dave".to_string()).unwrap();
+		assert_eq!(call_hash, sha256(b"\"pay dave\""));
+
+		assert_eq!(
+			multisig.approve_as_multi(
+				&mut system,
+				&mut balances,
+				alice.clone(),
+				2,
+				vec![bob.clone(), bob.clone()],
+				call_hash
+			),
+			Err("signatories must be unique")
+		);
+		assert_eq!(
+			multisig.approve_as_multi(
+				&mut system,
+				&mut balances,
+				alice.clone(),
+				4,
+				others(&alice),
+				call_hash
+			),
+			Err("threshold must be at least two, and at most the number of signatories")
+		);
+		assert_eq!(
+			multisig.approve_as_multi(
+				&mut system,
+				&mut balances,
+				dave.clone(),
+				2,
+				others(&dave),
+				call_hash
+			),
+			Err("too many signatories")
+		);
+
+		// The first approval reserves the deposit, and later ones only add to the approvals.
+		let approve =
+			|multisig: &mut Pallet<TestConfig>, system: &mut _, balances: &mut _, who: &String| {
+				multisig.approve_as_multi(system, balances, who.clone(), 2, others(who), call_hash)
+			};
+		assert_eq!(approve(&mut multisig, &mut system, &mut balances, &alice), Ok(()));
+		assert_eq!(balances.reserved_balance(&alice), 10);
+		// The depositor is kept alive until the operation is executed or cancelled.
+		assert_eq!(system.account(&alice).consumers, 1);
+		assert_eq!(
+			approve(&mut multisig, &mut system, &mut balances, &alice),
+			Err("already approved")
+		);
+		assert_eq!(approve(&mut multisig, &mut system, &mut balances, &charlie), Ok(()));
+		let pending = multisig.multisig(&multi, &call_hash).unwrap();
+		assert_eq!(pending.approvals, [alice.clone(), charlie.clone()]);
+
+		// Meeting the threshold is not enough, the call has to be provided too.
+		assert_eq!(
+			multisig.as_multi(&mut system, &mut balances, bob.clone(), 2, others(&bob), call_hash),
+			Ok(Some(multi.clone()))
+		);
+		assert_eq!(multisig.multisig(&multi, &call_hash), None);
+		assert_eq!(balances.reserved_balance(&alice), 0);
+		assert_eq!(system.account(&alice).consumers, 0);
+
+		// Only the depositor can cancel a pending operation.
+		assert_eq!(
+			multisig.as_multi(&mut system, &mut balances, bob.clone(), 2, others(&bob), call_hash),
+			Ok(None)
+		);
+		assert_eq!(
+			multisig.cancel_as_multi(
+				&mut system,
+				&mut balances,
+				alice.clone(),
+				2,
+				others(&alice),
+				call_hash
+			),
+			Err("only the depositor can cancel")
+		);
+		assert_eq!(
+			multisig.cancel_as_multi(
+				&mut system,
+				&mut balances,
+				bob.clone(),
+				2,
+				others(&bob),
+				call_hash
+			),
+			Ok(())
+		);
+		assert_eq!(balances.reserved_balance(&bob), 0);
+		assert_eq!(system.account(&bob).consumers, 0);
+		assert_eq!(
+			system.events().last(),
+			Some(&TestEvent::Multisig(Event::MultisigCancelled {
+				cancelling: bob,
+				multisig: multi,
+				call_hash
+			}))
+		);
+	}
+}
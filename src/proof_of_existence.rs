@@ -1,91 +1,1342 @@
-use crate::support::DispatchResult;
-use std::collections::BTreeMap;
-use std::fmt::Debug;
+use crate::{
+	merkle,
+	support::{Currency, DispatchResult, Moment, Origin, ReservableCurrency},
+	system,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+	collections::{BTreeMap, BTreeSet},
+	fmt::Debug,
+	fs, io,
+	path::Path,
+};
 
-pub trait Config: crate::system::Config {
-	type Content: Debug + Ord;
+pub trait Config: crate::system::Config + Sized {
+	/// What claims are made on. This is a digest of the claimed document, so documents of any size
+	/// are stored under a fixed size key.
+	type Content: Debug + Ord + Clone + AsRef<[u8]>;
+	/// The currency claim deposits are taken in.
+	type Currency: ReservableCurrency<Self>;
+	/// The amount reserved from the claimer's balance for as long as a claim exists.
+	const CLAIM_DEPOSIT: BalanceOf<Self>;
+	/// The most expired claims removed when a block is finalized. Any more are left for the
+	/// following blocks, so cleanup cost stays predictable.
+	const MAX_EXPIRIES_PER_BLOCK: usize;
+	/// The most claims a single account can own at once.
+	const MAX_CLAIMS_PER_OWNER: usize;
+
+	/// Hash a document into the `Content` it is claimed under.
+	fn hash(document: &[u8]) -> Self::Content;
+}
+
+pub type BalanceOf<T> = <<T as Config>::Currency as Currency<T>>::Balance;
+
+/// The ways a call to this pallet can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+	AlreadyClaimed,
+	NoSuchClaim,
+	NotClaimOwner,
+	MemoTooLong,
+	NotOffered,
+	/// The caller cannot afford to reserve `Config::CLAIM_DEPOSIT`.
+	InsufficientBalanceForDeposit,
+	/// The currency refused to reserve the deposit for another reason, e.g. the funds are locked.
+	Currency(&'static str),
+	ExpiryInPast,
+	/// Renewing must move the expiry of a claim later. Claims without an expiry never expire.
+	ExpiryNotExtended,
+	/// The account would own more than `Config::MAX_CLAIMS_PER_OWNER` claims.
+	TooManyClaims,
+	/// Jointly owned claims can only be revoked, renewed or transferred with `approve_action`.
+	JointlyOwned,
+	/// A threshold must be at least one, and at most the number of owners.
+	InvalidThreshold,
+	AlreadyApproved,
+}
+
+impl From<Error> for &'static str {
+	fn from(error: Error) -> Self {
+		match error {
+			Error::AlreadyClaimed => "this content is already claimed",
+			Error::NoSuchClaim => "claim does not exist",
+			Error::NotClaimOwner => "this content is owned by someone else",
+			Error::MemoTooLong => "memo is too long",
+			Error::NotOffered => "claim has not been offered to you",
+			Error::InsufficientBalanceForDeposit => "insufficient balance for the claim deposit",
+			Error::Currency(error) => error,
+			Error::ExpiryInPast => "expiry block has already passed",
+			Error::ExpiryNotExtended => "claims can only be renewed to a later expiry",
+			Error::TooManyClaims => "account owns too many claims",
+			Error::JointlyOwned => "jointly owned claims need the approval of their owners",
+			Error::InvalidThreshold => "threshold must be between one and the number of owners",
+			Error::AlreadyApproved => "this action has already been approved by you",
+		}
+	}
 }
 
-#[derive(Debug)]
+/// The longest memo which can be attached to a claim, in bytes.
+pub const MAX_MEMO_LEN: usize = 256;
+
+/// Everything recorded about a claim. The creation block and timestamp are what prove the claimed
+/// content existed at that point, so they are kept even if the owner changes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClaimInfo<AccountId, BlockNumber, Balance> {
+	pub owner: AccountId,
+	pub created_at_block: BlockNumber,
+	pub created_at_timestamp: Moment,
+	/// A free form description of the claimed content.
+	pub memo: String,
+	/// The account the deposit is reserved from. This is the creator of the claim, until an
+	/// account accepts the claim with `accept_claim` and takes the deposit over. A direct transfer
+	/// leaves the deposit where it is, since the new owner never agreed to pay it.
+	pub depositor: AccountId,
+	pub deposit: Balance,
+	/// The last block the claim exists in, if it is not kept forever. It is removed when that
+	/// block is finalized, or soon after if many claims expire at once.
+	pub expires_at: Option<BlockNumber>,
+	/// Whether the claimed content is the Merkle root of a batch of documents, rather than the
+	/// digest of a single document.
+	pub batch: bool,
+	/// Accounts which own the claim together with `owner`. While there are any, the claim can only
+	/// be revoked, renewed or transferred once `threshold` of its owners approve.
+	pub co_owners: Vec<AccountId>,
+	pub threshold: u16,
+}
+
+impl<AccountId: PartialEq, BlockNumber, Balance> ClaimInfo<AccountId, BlockNumber, Balance> {
+	/// The owner and every co-owner of the claim.
+	pub fn owners(&self) -> impl Iterator<Item = &AccountId> {
+		std::iter::once(&self.owner).chain(&self.co_owners)
+	}
+
+	pub fn is_owner(&self, who: &AccountId) -> bool {
+		self.owners().any(|owner| owner == who)
+	}
+}
+
+/// What the owners of a jointly owned claim can approve doing with it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JointAction<AccountId, BlockNumber> {
+	Revoke,
+	/// Transfer the claim to `new_owner`, who becomes its only owner.
+	Transfer {
+		new_owner: AccountId,
+	},
+	/// Move the expiry of the claim to the later block `expires_at`.
+	Renew {
+		expires_at: BlockNumber,
+	},
+}
+
+/// An action on a jointly owned claim, and the owners who have approved it so far. The first
+/// approval is from the owner who proposed the action.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingApproval<AccountId, BlockNumber> {
+	pub action: JointAction<AccountId, BlockNumber>,
+	pub approvals: Vec<AccountId>,
+}
+
+pub type ClaimInfoOf<T> =
+	ClaimInfo<<T as system::Config>::AccountId, <T as system::Config>::BlockNumber, BalanceOf<T>>;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "T::Content: Serialize + DeserializeOwned, T::AccountId: Serialize + \
+	DeserializeOwned, T::BlockNumber: Serialize + DeserializeOwned, BalanceOf<T>: Serialize + \
+	DeserializeOwned")]
 pub struct Pallet<T: Config> {
-	claims: BTreeMap<T::Content, T::AccountId>,
+	#[serde(with = "crate::support::storage_map")]
+	// Spelled out rather than `ClaimInfoOf<T>`, so the `Debug` derive sees which associated types
+	// need to implement `Debug`.
+	#[allow(clippy::type_complexity)]
+	claims: BTreeMap<
+		T::Content,
+		ClaimInfo<T::AccountId, T::BlockNumber, <T::Currency as Currency<T>>::Balance>,
+	>,
+	/// Claims their owner has offered to another account, which that account has yet to accept.
+	#[serde(with = "crate::support::storage_map")]
+	offers: BTreeMap<T::Content, T::AccountId>,
+	/// Claims by the block they expire in, so finalization does not need to scan every claim.
+	#[serde(with = "crate::support::storage_map")]
+	expiries: BTreeMap<T::BlockNumber, Vec<T::Content>>,
+	/// The claims each account owns or co-owns, kept in step with the owners of every claim.
+	#[serde(with = "crate::support::storage_map")]
+	claims_by_owner: BTreeMap<T::AccountId, BTreeSet<T::Content>>,
+	/// Actions on jointly owned claims which have yet to reach their threshold of approvals. Each
+	/// owner has at most one proposal pending per claim, so this stays bounded by the owners.
+	#[serde(with = "crate::support::storage_map")]
+	#[allow(clippy::type_complexity)]
+	approvals: BTreeMap<T::Content, Vec<PendingApproval<T::AccountId, T::BlockNumber>>>,
 }
 
 impl<T: Config> Pallet<T> {
 	pub fn new() -> Self {
-		Self { claims: BTreeMap::new() }
+		Self {
+			claims: BTreeMap::new(),
+			offers: BTreeMap::new(),
+			expiries: BTreeMap::new(),
+			claims_by_owner: BTreeMap::new(),
+			approvals: BTreeMap::new(),
+		}
+	}
+
+	/// The claim on `claim`, unless there is none or it has expired by the current block. Expired
+	/// claims may stay in storage for a few blocks before `on_finalize` removes them.
+	pub fn get_claim(
+		&self,
+		system: &system::Pallet<T>,
+		claim: &T::Content,
+	) -> Option<ClaimInfoOf<T>> {
+		let info = self.claims.get(claim)?;
+		match info.expires_at {
+			Some(at) if at < system.get_block_number() => None,
+			_ => Some(info.clone()),
+		}
+	}
+
+	/// Up to `limit` of the claims owned by `owner`, skipping the first `start`. Claims are ordered
+	/// by their content, so pages stay stable as long as the owner's claims do not change.
+	pub fn claims_of(&self, owner: &T::AccountId, start: usize, limit: usize) -> Vec<T::Content> {
+		match self.claims_by_owner.get(owner) {
+			Some(claims) => claims.iter().skip(start).take(limit).cloned().collect(),
+			None => Vec::new(),
+		}
 	}
 
-	pub fn get_claim(&self, claim: &T::Content) -> Option<T::AccountId> {
-		self.claims.get(claim).cloned()
+	/// How many claims `owner` owns.
+	pub fn claim_count(&self, owner: &T::AccountId) -> usize {
+		self.claims_by_owner.get(owner).map_or(0, BTreeSet::len)
 	}
 
-	pub fn create_claim(&mut self, who: T::AccountId, claim: T::Content) -> DispatchResult {
-		// self.claims.contains_key(&claim)
-		if let Some(_) = self.claims.get(&claim) {
-			return Err("this content is already claimed");
+	/// Hash the file at `path`, giving the `Content` to claim it under.
+	pub fn hash_file(path: impl AsRef<Path>) -> io::Result<T::Content> {
+		Ok(T::hash(&fs::read(path)?))
+	}
+
+	/// Look up the claim on `document`, returning its digest and claim details if it has been
+	/// claimed.
+	pub fn verify_document(
+		&self,
+		system: &system::Pallet<T>,
+		document: &[u8],
+	) -> Option<(T::Content, ClaimInfoOf<T>)> {
+		let digest = T::hash(document);
+		let info = self.get_claim(system, &digest)?;
+		Some((digest, info))
+	}
+
+	/// Claim `claim` for `who`, recording the current block and `now` as its creation time.
+	/// `Config::CLAIM_DEPOSIT` is reserved from `who` until the claim is revoked or expires.
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_claim(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		currency: &mut T::Currency,
+		now: Moment,
+		who: T::AccountId,
+		claim: T::Content,
+		memo: String,
+		expires_at: Option<T::BlockNumber>,
+	) -> Result<(), Error> {
+		if self.get_claim(system, &claim).is_some() {
+			return Err(Error::AlreadyClaimed);
+		}
+		if memo.len() > MAX_MEMO_LEN {
+			return Err(Error::MemoTooLong);
 		}
-		self.claims.insert(claim, who);
+		if expires_at.is_some_and(|at| at < system.get_block_number()) {
+			return Err(Error::ExpiryInPast);
+		}
+		self.ensure_can_own(&who)?;
+		Self::reserve_deposit(system, currency, &who, T::CLAIM_DEPOSIT)?;
+		// An expired claim which has not been cleaned up yet makes way for the new one.
+		self.remove_claim(system, currency, &claim);
+		let info = ClaimInfo {
+			owner: who.clone(),
+			created_at_block: system.get_block_number(),
+			created_at_timestamp: now,
+			memo,
+			depositor: who,
+			deposit: T::CLAIM_DEPOSIT,
+			expires_at,
+			batch: false,
+			co_owners: Vec::new(),
+			threshold: 1,
+		};
+		if let Some(at) = expires_at {
+			self.expiries.entry(at).or_default().push(claim.clone());
+		}
+		self.claims_by_owner
+			.entry(info.owner.clone())
+			.or_default()
+			.insert(claim.clone());
+		self.claims.insert(claim, info);
 		Ok(())
 	}
 
-	pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> DispatchResult {
-		let owner = self.get_claim(&claim).ok_or("claim does not exist")?;
-		if caller != owner {
-			return Err(&"this content is owned by someone else");
+	/// Claim `root`, the root of a `merkle::MerkleTree` over the digests of many documents, in one
+	/// go. Each document can then be shown to be part of the batch with `verify_batched_document`.
+	#[allow(clippy::too_many_arguments)]
+	pub fn create_batch_claim(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		currency: &mut T::Currency,
+		now: Moment,
+		who: T::AccountId,
+		root: T::Content,
+		memo: String,
+		expires_at: Option<T::BlockNumber>,
+	) -> Result<(), Error> {
+		self.create_claim(system, currency, now, who, root.clone(), memo, expires_at)?;
+		if let Some(info) = self.claims.get_mut(&root) {
+			info.batch = true;
 		}
-		self.claims.remove(&claim);
 		Ok(())
 	}
-}
 
-pub enum Call<T: Config> {
-	CreateClaim { claim: T::Content },
-	RevokeClaim { claim: T::Content },
-}
+	/// Check that `leaf` is part of the Merkle tree with the given `root`, using a proof from
+	/// `merkle::MerkleTree::proof`. This does not check that `root` has been claimed.
+	pub fn verify_inclusion(root: &T::Content, leaf: &T::Content, proof: &[T::Content]) -> bool {
+		merkle::verify_inclusion(root, leaf, proof, T::hash)
+	}
+
+	/// Look up the batch claim on `root`, returning its details if `document` is part of it.
+	pub fn verify_batched_document(
+		&self,
+		system: &system::Pallet<T>,
+		root: &T::Content,
+		document: &[u8],
+		proof: &[T::Content],
+	) -> Option<ClaimInfoOf<T>> {
+		let info = self.get_claim(system, root).filter(|info| info.batch)?;
+		Self::verify_inclusion(root, &T::hash(document), proof).then_some(info)
+	}
+
+	/// Remove `claim`, returning its deposit to the account which created it.
+	pub fn revoke_claim(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		claim: T::Content,
+	) -> Result<(), Error> {
+		Self::ensure_sole_owner(&self.ensure_owner(system, &caller, &claim)?)?;
+		self.remove_claim(system, currency, &claim);
+		Ok(())
+	}
+
+	/// Turn `claim`, owned by `caller` alone, into a jointly owned claim. Afterwards revoking,
+	/// renewing or transferring it needs the approval of `threshold` of its owners, through
+	/// `approve_action`.
+	pub fn share_claim(
+		&mut self,
+		system: &system::Pallet<T>,
+		caller: T::AccountId,
+		claim: T::Content,
+		mut co_owners: Vec<T::AccountId>,
+		threshold: u16,
+	) -> Result<(), Error> {
+		let mut info = self.ensure_owner(system, &caller, &claim)?;
+		Self::ensure_sole_owner(&info)?;
+		co_owners.sort();
+		co_owners.dedup();
+		co_owners.retain(|co_owner| *co_owner != caller);
+		if threshold == 0 || threshold as usize > co_owners.len() + 1 {
+			return Err(Error::InvalidThreshold);
+		}
+		for co_owner in &co_owners {
+			self.ensure_can_own(co_owner)?;
+		}
+		for co_owner in &co_owners {
+			self.claims_by_owner.entry(co_owner.clone()).or_default().insert(claim.clone());
+		}
+		info.co_owners = co_owners;
+		info.threshold = threshold;
+		self.claims.insert(claim.clone(), info);
+		self.offers.remove(&claim);
+		Ok(())
+	}
+
+	/// Approve `action` on `claim` as one of its owners. The action is carried out as soon as
+	/// `threshold` owners have approved it, which for a claim with a single owner is straight away.
+	///
+	/// Only actions which could be carried out right now are accepted. Proposing a new action
+	/// replaces the caller's previous proposal on the claim, along with its approvals.
+	pub fn approve_action(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		claim: T::Content,
+		action: JointAction<T::AccountId, T::BlockNumber>,
+	) -> Result<(), Error> {
+		let info = self.ensure_owner(system, &caller, &claim)?;
+		self.ensure_valid_action(system, &info, &action)?;
+		let approvals = self
+			.approvals
+			.get(&claim)
+			.and_then(|pending| pending.iter().find(|pending| pending.action == action))
+			.map_or(&[][..], |pending| &pending.approvals[..]);
+		if approvals.contains(&caller) {
+			return Err(Error::AlreadyApproved);
+		}
+		if approvals.len() + 1 < info.threshold as usize {
+			let pending = self.approvals.entry(claim).or_default();
+			match pending.iter_mut().find(|pending| pending.action == action) {
+				Some(pending) => pending.approvals.push(caller),
+				None => {
+					pending.retain(|pending| pending.approvals.first() != Some(&caller));
+					pending.push(PendingApproval { action, approvals: vec![caller] });
+				},
+			}
+			return Ok(());
+		}
+		match &action {
+			JointAction::Revoke => self.remove_claim(system, currency, &claim),
+			JointAction::Transfer { new_owner } => {
+				self.set_owner(claim, info, new_owner.clone())?
+			},
+			JointAction::Renew { expires_at } => {
+				self.set_expiry(system, claim.clone(), info, *expires_at)?;
+				// The claim keeps its owners, so approvals of other actions still stand.
+				if let Some(pending) = self.approvals.get_mut(&claim) {
+					pending.retain(|pending| pending.action != action);
+					if pending.is_empty() {
+						self.approvals.remove(&claim);
+					}
+				}
+			},
+		}
+		Ok(())
+	}
+
+	/// The actions on `claim` which are waiting for more of its owners to approve them.
+	#[cfg(test)]
+	pub fn pending_approvals(
+		&self,
+		claim: &T::Content,
+	) -> Vec<PendingApproval<T::AccountId, T::BlockNumber>> {
+		self.approvals.get(claim).cloned().unwrap_or_default()
+	}
+
+	/// Move the expiry of `claim` to the later block `expires_at`.
+	pub fn renew_claim(
+		&mut self,
+		system: &system::Pallet<T>,
+		caller: T::AccountId,
+		claim: T::Content,
+		expires_at: T::BlockNumber,
+	) -> Result<(), Error> {
+		let info = self.ensure_owner(system, &caller, &claim)?;
+		Self::ensure_sole_owner(&info)?;
+		self.set_expiry(system, claim, info, expires_at)
+	}
+
+	fn set_expiry(
+		&mut self,
+		system: &system::Pallet<T>,
+		claim: T::Content,
+		mut info: ClaimInfoOf<T>,
+		expires_at: T::BlockNumber,
+	) -> Result<(), Error> {
+		let old = Self::ensure_extends(system, &info, expires_at)?;
+		self.unschedule_expiry(&claim, old);
+		self.expiries.entry(expires_at).or_default().push(claim.clone());
+		info.expires_at = Some(expires_at);
+		self.claims.insert(claim, info);
+		Ok(())
+	}
+
+	/// Check `expires_at` is a later expiry for the claim `info`, still in the future, and return
+	/// the current expiry.
+	fn ensure_extends(
+		system: &system::Pallet<T>,
+		info: &ClaimInfoOf<T>,
+		expires_at: T::BlockNumber,
+	) -> Result<T::BlockNumber, Error> {
+		if expires_at < system.get_block_number() {
+			return Err(Error::ExpiryInPast);
+		}
+		match info.expires_at {
+			Some(old) if old < expires_at => Ok(old),
+			_ => Err(Error::ExpiryNotExtended),
+		}
+	}
+
+	/// Check `action` could be carried out on the claim `info` as it is now, so that approvals
+	/// are only collected for actions which can still succeed.
+	fn ensure_valid_action(
+		&self,
+		system: &system::Pallet<T>,
+		info: &ClaimInfoOf<T>,
+		action: &JointAction<T::AccountId, T::BlockNumber>,
+	) -> Result<(), Error> {
+		match action {
+			JointAction::Revoke => Ok(()),
+			JointAction::Transfer { new_owner } if info.is_owner(new_owner) => Ok(()),
+			JointAction::Transfer { new_owner } => self.ensure_can_own(new_owner),
+			JointAction::Renew { expires_at } => {
+				Self::ensure_extends(system, info, *expires_at).map(|_| ())
+			},
+		}
+	}
 
-impl<T: Config> crate::support::Dispatch for Pallet<T> {
-	type Caller = T::AccountId;
-	type Call = Call<T>;
+	/// Remove claims which have expired by the current block, returning their deposits. At most
+	/// `Config::MAX_EXPIRIES_PER_BLOCK` claims are removed, oldest expiry first.
+	pub fn on_finalize(&mut self, system: &mut system::Pallet<T>, currency: &mut T::Currency) {
+		let expired: Vec<T::Content> = self
+			.expiries
+			.range(..=system.get_block_number())
+			.flat_map(|(_, claims)| claims.iter().cloned())
+			.take(T::MAX_EXPIRIES_PER_BLOCK)
+			.collect();
+		for claim in expired {
+			self.remove_claim(system, currency, &claim);
+		}
+	}
+
+	/// Hand `claim` over to `new_owner` directly. The claim keeps its creation details and its
+	/// deposit, which stays reserved from the current depositor. `new_owner` did not ask for the
+	/// claim, but can revoke it to free up room under `Config::MAX_CLAIMS_PER_OWNER`.
+	pub fn transfer_claim(
+		&mut self,
+		system: &system::Pallet<T>,
+		caller: T::AccountId,
+		claim: T::Content,
+		new_owner: T::AccountId,
+	) -> Result<(), Error> {
+		let info = self.ensure_owner(system, &caller, &claim)?;
+		Self::ensure_sole_owner(&info)?;
+		self.set_owner(claim, info, new_owner)
+	}
+
+	/// Offer `claim` to `to`, who becomes the owner once they accept it with `accept_claim`. The
+	/// claim stays with its current owner until then, and a new offer replaces any previous one.
+	pub fn offer_claim(
+		&mut self,
+		system: &system::Pallet<T>,
+		caller: T::AccountId,
+		claim: T::Content,
+		to: T::AccountId,
+	) -> Result<(), Error> {
+		Self::ensure_sole_owner(&self.ensure_owner(system, &caller, &claim)?)?;
+		self.offers.insert(claim, to);
+		Ok(())
+	}
+
+	/// Accept a claim previously offered to `caller`, who takes over its deposit.
+	pub fn accept_claim(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		currency: &mut T::Currency,
+		caller: T::AccountId,
+		claim: T::Content,
+	) -> Result<(), Error> {
+		match self.offers.get(&claim) {
+			Some(to) if *to == caller => {},
+			_ => return Err(Error::NotOffered),
+		}
+		let mut info = self.get_claim(system, &claim).ok_or(Error::NoSuchClaim)?;
+		Self::ensure_sole_owner(&info)?;
+		if !info.is_owner(&caller) {
+			self.ensure_can_own(&caller)?;
+		}
+		Self::reserve_deposit(system, currency, &caller, info.deposit)?;
+		Self::release_deposit(system, currency, &info.depositor, info.deposit);
+		info.depositor = caller.clone();
+		self.set_owner(claim, info, caller)
+	}
+
+	#[cfg(test)]
+	pub fn pending_offer(&self, claim: &T::Content) -> Option<T::AccountId> {
+		self.offers.get(claim).cloned()
+	}
+
+	/// Make `new_owner` the only owner of `claim`, dropping any co-owners, offer or approvals. The
+	/// deposit is left as it is in `info`.
+	fn set_owner(
+		&mut self,
+		claim: T::Content,
+		mut info: ClaimInfoOf<T>,
+		new_owner: T::AccountId,
+	) -> Result<(), Error> {
+		if !info.is_owner(&new_owner) {
+			self.ensure_can_own(&new_owner)?;
+		}
+		for owner in info.owners() {
+			self.unindex_owner(owner, &claim);
+		}
+		self.claims_by_owner.entry(new_owner.clone()).or_default().insert(claim.clone());
+		info.owner = new_owner;
+		info.co_owners.clear();
+		info.threshold = 1;
+		self.claims.insert(claim.clone(), info);
+		self.offers.remove(&claim);
+		self.approvals.remove(&claim);
+		Ok(())
+	}
+
+	/// Remove `claim` along with any offer, approvals and scheduled expiry, and return its deposit.
+	fn remove_claim(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		currency: &mut T::Currency,
+		claim: &T::Content,
+	) {
+		let Some(info) = self.claims.remove(claim) else { return };
+		self.offers.remove(claim);
+		self.approvals.remove(claim);
+		if let Some(at) = info.expires_at {
+			self.unschedule_expiry(claim, at);
+		}
+		for owner in info.owners() {
+			self.unindex_owner(owner, claim);
+		}
+		Self::release_deposit(system, currency, &info.depositor, info.deposit);
+	}
+
+	/// Reserve a claim deposit from `who`, who then cannot be reaped until it is released. Only
+	/// a free balance below the deposit is reported as `InsufficientBalanceForDeposit`; other
+	/// failures are passed on as they are.
+	fn reserve_deposit(
+		system: &mut system::Pallet<T>,
+		currency: &mut T::Currency,
+		who: &T::AccountId,
+		deposit: BalanceOf<T>,
+	) -> Result<(), Error> {
+		if currency.free_balance(who) < deposit {
+			return Err(Error::InsufficientBalanceForDeposit);
+		}
+		currency.reserve(who, deposit).map_err(Error::Currency)?;
+		if let Err(error) = system.inc_consumers(who) {
+			currency.unreserve(who, deposit);
+			return Err(Error::Currency(error));
+		}
+		Ok(())
+	}
+
+	fn release_deposit(
+		system: &mut system::Pallet<T>,
+		currency: &mut T::Currency,
+		who: &T::AccountId,
+		deposit: BalanceOf<T>,
+	) {
+		currency.unreserve(who, deposit);
+		system.dec_consumers(who);
+	}
+
+	fn unindex_owner(&mut self, owner: &T::AccountId, claim: &T::Content) {
+		if let Some(claims) = self.claims_by_owner.get_mut(owner) {
+			claims.remove(claim);
+			if claims.is_empty() {
+				self.claims_by_owner.remove(owner);
+			}
+		}
+	}
+
+	fn ensure_can_own(&self, who: &T::AccountId) -> Result<(), Error> {
+		if self.claim_count(who) >= T::MAX_CLAIMS_PER_OWNER {
+			return Err(Error::TooManyClaims);
+		}
+		Ok(())
+	}
+
+	fn unschedule_expiry(&mut self, claim: &T::Content, at: T::BlockNumber) {
+		if let Some(claims) = self.expiries.get_mut(&at) {
+			claims.retain(|c| c != claim);
+			if claims.is_empty() {
+				self.expiries.remove(&at);
+			}
+		}
+	}
+
+	fn ensure_owner(
+		&self,
+		system: &system::Pallet<T>,
+		who: &T::AccountId,
+		claim: &T::Content,
+	) -> Result<ClaimInfoOf<T>, Error> {
+		let info = self.get_claim(system, claim).ok_or(Error::NoSuchClaim)?;
+		if !info.is_owner(who) {
+			return Err(Error::NotClaimOwner);
+		}
+		Ok(info)
+	}
 
-	fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult {
+	fn ensure_sole_owner(info: &ClaimInfoOf<T>) -> Result<(), Error> {
+		if !info.co_owners.is_empty() {
+			return Err(Error::JointlyOwned);
+		}
+		Ok(())
+	}
+
+	/// Dispatch a call to this pallet. `now` is the current time, which is recorded on new claims.
+	pub fn dispatch(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		currency: &mut T::Currency,
+		now: Moment,
+		origin: Origin<T::AccountId>,
+		call: Call<T>,
+	) -> DispatchResult {
+		let caller = origin.ensure_signed()?;
 		match call {
-			Call::CreateClaim { claim } => {
-				self.create_claim(caller, claim)?;
+			Call::CreateClaim { claim, memo, expires_at } => {
+				self.create_claim(system, currency, now, caller, claim, memo, expires_at)?;
+			},
+			Call::CreateBatchClaim { root, memo, expires_at } => {
+				self.create_batch_claim(system, currency, now, caller, root, memo, expires_at)?;
 			},
 			Call::RevokeClaim { claim } => {
-				self.revoke_claim(caller, claim)?;
+				self.revoke_claim(system, currency, caller, claim)?;
+			},
+			Call::RenewClaim { claim, expires_at } => {
+				self.renew_claim(system, caller, claim, expires_at)?;
+			},
+			Call::TransferClaim { claim, new_owner } => {
+				self.transfer_claim(system, caller, claim, new_owner)?;
+			},
+			Call::OfferClaim { claim, to } => {
+				self.offer_claim(system, caller, claim, to)?;
+			},
+			Call::AcceptClaim { claim } => {
+				self.accept_claim(system, currency, caller, claim)?;
+			},
+			Call::ShareClaim { claim, co_owners, threshold } => {
+				self.share_claim(system, caller, claim, co_owners, threshold)?;
+			},
+			Call::ApproveAction { claim, action } => {
+				self.approve_action(system, currency, caller, claim, action)?;
 			},
 		}
 		Ok(())
 	}
 }
 
+// The variants mirror the names of the functions they call.
+#[allow(clippy::enum_variant_names)]
+#[derive(Serialize)]
+#[serde(bound = "T::Content: Serialize, T::AccountId: Serialize, T::BlockNumber: Serialize")]
+pub enum Call<T: Config> {
+	CreateClaim { claim: T::Content, memo: String, expires_at: Option<T::BlockNumber> },
+	CreateBatchClaim { root: T::Content, memo: String, expires_at: Option<T::BlockNumber> },
+	RevokeClaim { claim: T::Content },
+	RenewClaim { claim: T::Content, expires_at: T::BlockNumber },
+	TransferClaim { claim: T::Content, new_owner: T::AccountId },
+	OfferClaim { claim: T::Content, to: T::AccountId },
+	AcceptClaim { claim: T::Content },
+	ShareClaim { claim: T::Content, co_owners: Vec<T::AccountId>, threshold: u16 },
+	ApproveAction { claim: T::Content, action: JointAction<T::AccountId, T::BlockNumber> },
+}
+
 #[cfg(test)]
 mod poe_tests {
+	use super::{ClaimInfo, Error, JointAction, PendingApproval};
+	use crate::support::{sha256, Hash};
+
 	struct TestConfig;
 
 	impl super::Config for TestConfig {
-		type Content = &'static str;
+		type Content = Hash;
+		type Currency = crate::balances::Pallet<Self>;
+		const CLAIM_DEPOSIT: u128 = 10;
+		const MAX_EXPIRIES_PER_BLOCK: usize = 2;
+		const MAX_CLAIMS_PER_OWNER: usize = 4;
+
+		fn hash(document: &[u8]) -> Hash {
+			sha256(document)
+		}
+	}
+
+	impl crate::balances::Config for TestConfig {
+		type Balance = u128;
+		const EXISTENTIAL_DEPOSIT: u128 = 1;
 	}
 
 	impl crate::system::Config for TestConfig {
 		type AccountId = &'static str;
 		type BlockNumber = u32;
 		type Nonce = u32;
+		const BLOCK_HASH_COUNT: u32 = 250;
+		type RuntimeEvent = crate::system::Event<&'static str>;
+	}
+
+	/// Balances where every account used by the tests can afford a few claim deposits.
+	fn funded_balances(
+		system: &mut crate::system::Pallet<TestConfig>,
+	) -> crate::balances::Pallet<TestConfig> {
+		let mut balances = crate::balances::Pallet::new();
+		for who in ["alice", "bob", "charlie", "dave"] {
+			balances.set_balance(system, &who, 100).unwrap();
+		}
+		balances
 	}
 
 	#[test]
 	fn basic_proof_of_existence() {
+		let mut system = crate::system::Pallet::<TestConfig>::new();
+		let mut balances = funded_balances(&mut system);
 		let mut poe = super::Pallet::<TestConfig>::new();
-		assert_eq!(poe.get_claim(&"Hello, world!"), None);
-		assert_eq!(poe.create_claim(&"alice", &"Hello, world!"), Ok(()));
-		assert_eq!(poe.get_claim(&"Hello, world!"), Some("alice"));
+		let hello = sha256(b"Hello, world!");
+		assert_eq!(poe.get_claim(&system, &hello), None);
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 0, "alice", hello, String::new(), None),
+			Ok(())
+		);
+		assert_eq!(poe.get_claim(&system, &hello).map(|info| info.owner), Some("alice"));
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 0, "bob", hello, String::new(), None),
+			Err(Error::AlreadyClaimed)
+		);
+		assert_eq!(poe.revoke_claim(&mut system, &mut balances, "alice", hello), Ok(()));
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 0, "bob", hello, String::new(), None),
+			Ok(())
+		);
+	}
+
+	#[test]
+	fn claim_metadata() {
+		let mut system = crate::system::Pallet::<TestConfig>::new();
+		let mut balances = funded_balances(&mut system);
+		let mut poe = super::Pallet::<TestConfig>::new();
+		let hello = sha256(b"Hello, world!");
+
+		system.set_block_number();
+		system.set_block_number();
+		let memo = "x".repeat(super::MAX_MEMO_LEN + 1);
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 1_000, "alice", hello, memo, None),
+			Err(Error::MemoTooLong)
+		);
+		assert_eq!(
+			poe.create_claim(
+				&mut system,
+				&mut balances,
+				1_000,
+				"alice",
+				hello,
+				"greeting".into(),
+				None
+			),
+			Ok(())
+		);
+		assert_eq!(
+			poe.get_claim(&system, &hello),
+			Some(ClaimInfo {
+				owner: "alice",
+				created_at_block: 2,
+				created_at_timestamp: 1_000,
+				memo: "greeting".to_string(),
+				depositor: "alice",
+				deposit: 10,
+				expires_at: None,
+				batch: false,
+				co_owners: vec![],
+				threshold: 1,
+			})
+		);
+	}
+
+	#[test]
+	fn claim_documents() {
+		let mut poe = super::Pallet::<TestConfig>::new();
+
+		let path = std::env::temp_dir().join("rust-state-machine-poe-document.txt");
+		std::fs::write(&path, b"some document").unwrap();
+		let digest = super::Pallet::<TestConfig>::hash_file(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+		assert_eq!(digest, sha256(b"some document"));
+
+		let mut system = crate::system::Pallet::<TestConfig>::new();
+		let mut balances = funded_balances(&mut system);
+		assert_eq!(poe.verify_document(&system, b"some document"), None);
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 5, "alice", digest, String::new(), None),
+			Ok(())
+		);
+		let (verified, info) = poe.verify_document(&system, b"some document").unwrap();
+		assert_eq!((verified, info.owner, info.created_at_timestamp), (digest, "alice", 5));
+		assert_eq!(poe.verify_document(&system, b"some other document"), None);
+	}
+
+	#[test]
+	fn transfer_claims() {
+		let mut system = crate::system::Pallet::<TestConfig>::new();
+		let mut balances = funded_balances(&mut system);
+		let mut poe = super::Pallet::<TestConfig>::new();
+		let hello = sha256(b"Hello, world!");
+
+		system.set_block_number();
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 7, "alice", hello, "memo".into(), None),
+			Ok(())
+		);
+		let created = poe.get_claim(&system, &hello).unwrap();
+
+		assert_eq!(poe.transfer_claim(&system, "bob", hello, "bob"), Err(Error::NotClaimOwner));
+		assert_eq!(poe.transfer_claim(&system, "alice", hello, "bob"), Ok(()));
+		// The owner changes, but the claim still proves when the content existed.
+		assert_eq!(poe.get_claim(&system, &hello), Some(ClaimInfo { owner: "bob", ..created }));
+
+		// Offered claims stay with their owner until accepted.
+		assert_eq!(poe.offer_claim(&system, "bob", hello, "charlie"), Ok(()));
+		assert_eq!(poe.pending_offer(&hello), Some("charlie"));
+		assert_eq!(
+			poe.accept_claim(&mut system, &mut balances, "dave", hello),
+			Err(Error::NotOffered)
+		);
+		assert_eq!(poe.get_claim(&system, &hello).unwrap().owner, "bob");
+		assert_eq!(poe.accept_claim(&mut system, &mut balances, "charlie", hello), Ok(()));
+		assert_eq!(poe.get_claim(&system, &hello).unwrap().owner, "charlie");
+		assert_eq!(poe.pending_offer(&hello), None);
+
+		// Revoking a claim withdraws any pending offer.
+		assert_eq!(poe.offer_claim(&system, "charlie", hello, "dave"), Ok(()));
+		assert_eq!(poe.revoke_claim(&mut system, &mut balances, "charlie", hello), Ok(()));
+		assert_eq!(poe.pending_offer(&hello), None);
+		assert_eq!(
+			poe.accept_claim(&mut system, &mut balances, "dave", hello),
+			Err(Error::NotOffered)
+		);
+	}
+
+	#[test]
+	fn claim_deposits() {
+		let mut system = crate::system::Pallet::<TestConfig>::new();
+		let mut balances = funded_balances(&mut system);
+		let mut poe = super::Pallet::<TestConfig>::new();
+		let hello = sha256(b"Hello, world!");
+
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 0, "alice", hello, String::new(), None),
+			Ok(())
+		);
+		assert_eq!((balances.balance(&"alice"), balances.reserved_balance(&"alice")), (90, 10));
+		// While the deposit is held, the account cannot be reaped.
+		assert_eq!(system.account(&"alice").consumers, 1);
+
+		// A direct transfer cannot make the new owner pay, so the deposit stays where it is.
+		assert_eq!(poe.transfer_claim(&system, "alice", hello, "bob"), Ok(()));
+		assert_eq!((balances.balance(&"alice"), balances.reserved_balance(&"alice")), (90, 10));
+		assert_eq!((balances.balance(&"bob"), balances.reserved_balance(&"bob")), (100, 0));
+		assert_eq!(poe.get_claim(&system, &hello).unwrap().depositor, "alice");
+		assert_eq!((system.account(&"alice").consumers, system.account(&"bob").consumers), (1, 0));
+
+		// Accepting an offer takes the deposit over, and it is returned on revocation.
+		assert_eq!(poe.offer_claim(&system, "bob", hello, "charlie"), Ok(()));
+		assert_eq!(poe.accept_claim(&mut system, &mut balances, "charlie", hello), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+		assert_eq!(balances.reserved_balance(&"charlie"), 10);
+		assert_eq!(poe.get_claim(&system, &hello).unwrap().depositor, "charlie");
+		assert_eq!(
+			(system.account(&"alice").consumers, system.account(&"charlie").consumers),
+			(0, 1)
+		);
+		assert_eq!(poe.revoke_claim(&mut system, &mut balances, "charlie", hello), Ok(()));
+		assert_eq!((balances.balance(&"charlie"), balances.reserved_balance(&"charlie")), (100, 0));
+		assert_eq!(system.account(&"charlie").consumers, 0);
+
+		// A claim is only created if its deposit can be reserved.
+		balances.set_balance(&mut system, &"eve", 5).unwrap();
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 0, "eve", hello, String::new(), None),
+			Err(Error::InsufficientBalanceForDeposit)
+		);
+		assert_eq!(poe.get_claim(&system, &hello), None);
+		assert_eq!(balances.balance(&"eve"), 5);
+
+		// Nor can an offer be accepted by someone who cannot take over its deposit.
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 0, "alice", hello, String::new(), None),
+			Ok(())
+		);
+		assert_eq!(poe.offer_claim(&system, "alice", hello, "eve"), Ok(()));
+		assert_eq!(
+			poe.accept_claim(&mut system, &mut balances, "eve", hello),
+			Err(Error::InsufficientBalanceForDeposit)
+		);
+		// Failures other than a lack of funds are not reported as one.
+		balances.set_lock(*b"testlock", &"dave", 100, crate::balances::Reasons::All);
+		assert_eq!(poe.offer_claim(&system, "alice", hello, "dave"), Ok(()));
+		assert_eq!(
+			poe.accept_claim(&mut system, &mut balances, "dave", hello),
+			Err(Error::Currency("funds are locked"))
+		);
+		assert_eq!(poe.get_claim(&system, &hello).unwrap().owner, "alice");
+		assert_eq!(balances.reserved_balance(&"alice"), 10);
+	}
+
+	#[test]
+	fn claim_expiry() {
+		let mut system = crate::system::Pallet::<TestConfig>::new();
+		let mut balances = funded_balances(&mut system);
+		let mut poe = super::Pallet::<TestConfig>::new();
+		let [a, b, c, d] = [b"a", b"b", b"c", b"d"].map(|doc| sha256(doc));
+		let mut create = |poe: &mut super::Pallet<TestConfig>, claim, expires_at| {
+			poe.create_claim(
+				&mut system,
+				&mut balances,
+				0,
+				"alice",
+				claim,
+				String::new(),
+				expires_at,
+			)
+		};
+
+		assert_eq!(create(&mut poe, a, Some(2)), Ok(()));
+		assert_eq!(create(&mut poe, b, Some(2)), Ok(()));
+		assert_eq!(create(&mut poe, c, Some(2)), Ok(()));
+		assert_eq!(create(&mut poe, d, None), Ok(()));
+		assert_eq!(balances.reserved_balance(&"alice"), 40);
+
+		system.set_block_number();
+		assert_eq!(
+			poe.create_claim(
+				&mut system,
+				&mut balances,
+				0,
+				"bob",
+				sha256(b"e"),
+				String::new(),
+				Some(0)
+			),
+			Err(Error::ExpiryInPast)
+		);
+		assert_eq!(poe.renew_claim(&system, "alice", c, 2), Err(Error::ExpiryNotExtended));
+		assert_eq!(poe.renew_claim(&system, "alice", d, 5), Err(Error::ExpiryNotExtended));
+		assert_eq!(poe.renew_claim(&system, "alice", d, 0), Err(Error::ExpiryInPast));
+		assert_eq!(poe.renew_claim(&system, "alice", c, 3), Ok(()));
+		assert_eq!(poe.get_claim(&system, &c).unwrap().expires_at, Some(3));
+		poe.on_finalize(&mut system, &mut balances);
+		assert!(poe.get_claim(&system, &a).is_some());
+
+		// Claims expire at the end of their expiry block.
+		system.set_block_number();
+		poe.on_finalize(&mut system, &mut balances);
+		assert_eq!((poe.get_claim(&system, &a), poe.get_claim(&system, &b)), (None, None));
+		assert!(poe.get_claim(&system, &c).is_some());
+		assert_eq!(balances.reserved_balance(&"alice"), 20);
+
+		// Only `MAX_EXPIRIES_PER_BLOCK` claims are removed per block, the rest wait for the next.
+		for claim in [a, b] {
+			assert_eq!(
+				poe.create_claim(
+					&mut system,
+					&mut balances,
+					0,
+					"bob",
+					claim,
+					String::new(),
+					Some(3)
+				),
+				Ok(())
+			);
+		}
+		system.set_block_number();
+		poe.on_finalize(&mut system, &mut balances);
+		assert_eq!(poe.get_claim(&system, &c), None);
+		assert_eq!(poe.get_claim(&system, &a), None);
+		assert!(poe.get_claim(&system, &b).is_some());
+
+		// `b` has expired, and is only left in storage until the next block is finalized. It no
+		// longer counts as a claim in the meantime.
+		system.set_block_number();
+		assert_eq!(poe.get_claim(&system, &b), None);
+		assert_eq!(poe.verify_document(&system, b"b"), None);
+		assert_eq!(poe.renew_claim(&system, "bob", b, 10), Err(Error::NoSuchClaim));
+		assert_eq!(poe.revoke_claim(&mut system, &mut balances, "bob", b), Err(Error::NoSuchClaim));
+
+		// It can be claimed again straight away, which returns the old deposit.
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 0, "alice", b, String::new(), None),
+			Ok(())
+		);
+		assert_eq!(balances.reserved_balance(&"bob"), 0);
+		poe.on_finalize(&mut system, &mut balances);
+		assert_eq!(poe.get_claim(&system, &b).unwrap().owner, "alice");
+		assert!(poe.get_claim(&system, &d).is_some());
+		assert_eq!(balances.reserved_balance(&"alice"), 20);
+		assert!(poe.claims_of(&"bob", 0, 10).is_empty());
+	}
+
+	#[test]
+	fn batch_claims() {
+		let mut system = crate::system::Pallet::<TestConfig>::new();
+		let mut balances = funded_balances(&mut system);
+		let mut poe = super::Pallet::<TestConfig>::new();
+
+		let documents: Vec<&[u8]> = vec![b"one", b"two", b"three"];
+		let digests: Vec<Hash> = documents.iter().map(|doc| sha256(doc)).collect();
+		let tree = crate::merkle::MerkleTree::new(&digests, sha256).unwrap();
+		let root = *tree.root();
+		let proof = tree.proof(1).unwrap();
+
+		assert!(super::Pallet::<TestConfig>::verify_inclusion(&root, &digests[1], &proof));
+		assert_eq!(poe.verify_batched_document(&system, &root, documents[1], &proof), None);
+		assert_eq!(
+			poe.create_batch_claim(
+				&mut system,
+				&mut balances,
+				0,
+				"alice",
+				root,
+				String::new(),
+				None
+			),
+			Ok(())
+		);
+		// The whole batch costs a single deposit.
+		assert_eq!(balances.reserved_balance(&"alice"), 10);
+
+		let info = poe.verify_batched_document(&system, &root, documents[1], &proof).unwrap();
+		assert_eq!((info.owner, info.batch), ("alice", true));
+		assert_eq!(poe.verify_batched_document(&system, &root, documents[0], &proof), None);
+		assert_eq!(poe.verify_batched_document(&system, &root, b"four", &proof), None);
+
+		// Plain claims are not batches, even if a proof happens to check out against them.
+		let single = sha256(b"single");
+		let leaf_tree = crate::merkle::MerkleTree::new(&[single], sha256).unwrap();
+		let leaf_root = *leaf_tree.root();
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 0, "bob", leaf_root, String::new(), None),
+			Ok(())
+		);
+		assert_eq!(poe.verify_batched_document(&system, &leaf_root, b"single", &[]), None);
+	}
+
+	#[test]
+	fn claims_by_owner() {
+		let mut system = crate::system::Pallet::<TestConfig>::new();
+		let mut balances = funded_balances(&mut system);
+		let mut poe = super::Pallet::<TestConfig>::new();
+		let mut claims: Vec<Hash> = [b"a", b"b", b"c", b"d", b"e"].map(|doc| sha256(doc)).into();
+		claims.sort();
+		let [a, b, c, d, e] = claims[..] else { unreachable!() };
+
+		for claim in [a, b, c] {
+			assert_eq!(
+				poe.create_claim(
+					&mut system,
+					&mut balances,
+					0,
+					"alice",
+					claim,
+					String::new(),
+					None
+				),
+				Ok(())
+			);
+		}
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 0, "alice", d, String::new(), Some(1)),
+			Ok(())
+		);
+		assert_eq!(poe.claims_of(&"alice", 0, 10), vec![a, b, c, d]);
+		assert_eq!(poe.claims_of(&"alice", 1, 2), vec![b, c]);
+		assert!(poe.claims_of(&"alice", 4, 2).is_empty());
+		assert!(poe.claims_of(&"bob", 0, 10).is_empty());
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 0, "alice", e, String::new(), None),
+			Err(Error::TooManyClaims)
+		);
+
+		// The index follows transfers, revocations and expiry.
+		assert_eq!(poe.transfer_claim(&system, "alice", a, "bob"), Ok(()));
+		assert_eq!(poe.offer_claim(&system, "alice", b, "bob"), Ok(()));
+		assert_eq!(poe.accept_claim(&mut system, &mut balances, "bob", b), Ok(()));
+		assert_eq!(poe.revoke_claim(&mut system, &mut balances, "alice", c), Ok(()));
+		system.set_block_number();
+		poe.on_finalize(&mut system, &mut balances);
+		assert_eq!(poe.claims_of(&"bob", 0, 10), vec![a, b]);
+		assert_eq!(poe.claim_count(&"alice"), 0);
+
+		// The cap applies to claims received from others too.
+		for claim in [c, d] {
+			assert_eq!(
+				poe.create_claim(&mut system, &mut balances, 0, "bob", claim, String::new(), None),
+				Ok(())
+			);
+		}
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 0, "alice", e, String::new(), None),
+			Ok(())
+		);
+		assert_eq!(poe.transfer_claim(&system, "alice", e, "bob"), Err(Error::TooManyClaims));
+		assert_eq!(poe.claims_of(&"alice", 0, 10), vec![e]);
+	}
+
+	#[test]
+	fn joint_claims() {
+		let mut system = crate::system::Pallet::<TestConfig>::new();
+		let mut balances = funded_balances(&mut system);
+		let mut poe = super::Pallet::<TestConfig>::new();
+		let hello = sha256(b"Hello, world!");
+		let revoke = JointAction::Revoke;
+		let to_dave = JointAction::Transfer { new_owner: "dave" };
+
+		assert_eq!(
+			poe.create_claim(&mut system, &mut balances, 0, "alice", hello, String::new(), None),
+			Ok(())
+		);
+		assert_eq!(
+			poe.share_claim(&system, "alice", hello, vec!["bob", "charlie"], 4),
+			Err(Error::InvalidThreshold)
+		);
+		assert_eq!(
+			poe.share_claim(&system, "alice", hello, vec!["charlie", "bob", "bob"], 2),
+			Ok(())
+		);
+		let info = poe.get_claim(&system, &hello).unwrap();
+		assert_eq!((info.co_owners, info.threshold), (vec!["bob", "charlie"], 2));
+		assert_eq!(poe.claims_of(&"charlie", 0, 10), vec![hello]);
+
+		// No single owner can act alone any more.
+		assert_eq!(
+			poe.revoke_claim(&mut system, &mut balances, "alice", hello),
+			Err(Error::JointlyOwned)
+		);
+		assert_eq!(poe.transfer_claim(&system, "alice", hello, "dave"), Err(Error::JointlyOwned));
+		assert_eq!(poe.offer_claim(&system, "bob", hello, "dave"), Err(Error::JointlyOwned));
+
+		// Approvals are collected per action, across separate calls.
+		assert_eq!(
+			poe.approve_action(&mut system, &mut balances, "bob", hello, revoke.clone()),
+			Ok(())
+		);
+		assert_eq!(
+			poe.approve_action(&mut system, &mut balances, "bob", hello, revoke.clone()),
+			Err(Error::AlreadyApproved)
+		);
+		assert_eq!(
+			poe.approve_action(&mut system, &mut balances, "charlie", hello, to_dave.clone()),
+			Ok(())
+		);
+		assert_eq!(
+			poe.approve_action(&mut system, &mut balances, "dave", hello, to_dave.clone()),
+			Err(Error::NotClaimOwner)
+		);
+		assert_eq!(
+			poe.pending_approvals(&hello),
+			vec![
+				PendingApproval { action: revoke.clone(), approvals: vec!["bob"] },
+				PendingApproval { action: to_dave.clone(), approvals: vec!["charlie"] },
+			]
+		);
+
+		// Reaching the threshold carries the action out, and clears every pending approval.
+		assert_eq!(poe.approve_action(&mut system, &mut balances, "alice", hello, to_dave), Ok(()));
+		let info = poe.get_claim(&system, &hello).unwrap();
+		assert_eq!((info.owner, info.co_owners, info.threshold), ("dave", vec![], 1));
+		assert!(poe.pending_approvals(&hello).is_empty());
+		assert!(poe.claims_of(&"bob", 0, 10).is_empty());
+		assert_eq!(poe.claims_of(&"dave", 0, 10), vec![hello]);
+
+		// A sole owner's approval is enough on its own.
+		assert_eq!(poe.approve_action(&mut system, &mut balances, "dave", hello, revoke), Ok(()));
+		assert_eq!(poe.get_claim(&system, &hello), None);
+		assert_eq!(balances.reserved_balance(&"alice"), 0);
+	}
+
+	#[test]
+	fn joint_claim_renewal() {
+		let mut system = crate::system::Pallet::<TestConfig>::new();
+		let mut balances = funded_balances(&mut system);
+		let mut poe = super::Pallet::<TestConfig>::new();
+		let hello = sha256(b"Hello, world!");
+		let revoke = JointAction::Revoke;
+		let renew = JointAction::Renew { expires_at: 20 };
+
+		assert_eq!(
+			poe.create_claim(
+				&mut system,
+				&mut balances,
+				0,
+				"alice",
+				hello,
+				String::new(),
+				Some(10)
+			),
+			Ok(())
+		);
+		assert_eq!(poe.share_claim(&system, "alice", hello, vec!["bob"], 2), Ok(()));
+		assert_eq!(poe.renew_claim(&system, "alice", hello, 20), Err(Error::JointlyOwned));
+
+		assert_eq!(
+			poe.approve_action(&mut system, &mut balances, "bob", hello, revoke.clone()),
+			Ok(())
+		);
+		assert_eq!(
+			poe.approve_action(&mut system, &mut balances, "alice", hello, renew.clone()),
+			Ok(())
+		);
+		assert_eq!(poe.get_claim(&system, &hello).unwrap().expires_at, Some(10));
+		assert_eq!(poe.approve_action(&mut system, &mut balances, "bob", hello, renew), Ok(()));
+		assert_eq!(poe.get_claim(&system, &hello).unwrap().expires_at, Some(20));
+
+		// Renewing leaves the owners as they are, so other pending actions are kept.
+		assert_eq!(
+			poe.pending_approvals(&hello),
+			vec![PendingApproval { action: revoke, approvals: vec!["bob"] }]
+		);
+		// Actions which could not be carried out are not stored at all.
+		let renew_to = |expires_at| JointAction::Renew { expires_at };
+		assert_eq!(
+			poe.approve_action(&mut system, &mut balances, "alice", hello, renew_to(15)),
+			Err(Error::ExpiryNotExtended)
+		);
+		system.set_block_number();
+		system.set_block_number();
+		assert_eq!(
+			poe.approve_action(&mut system, &mut balances, "alice", hello, renew_to(1)),
+			Err(Error::ExpiryInPast)
+		);
+
+		// Each owner has one proposal pending at a time, however many they make.
+		for expires_at in 21..1_000 {
+			assert_eq!(
+				poe.approve_action(
+					&mut system,
+					&mut balances,
+					"alice",
+					hello,
+					renew_to(expires_at)
+				),
+				Ok(())
+			);
+		}
+		assert_eq!(
+			poe.pending_approvals(&hello),
+			vec![
+				PendingApproval { action: JointAction::Revoke, approvals: vec!["bob"] },
+				PendingApproval { action: renew_to(999), approvals: vec!["alice"] },
+			]
+		);
+		// A transfer to an account which cannot own another claim is refused up front too.
+		for document in [b"one", b"two", b"six", b"ten"] {
+			let claim = sha256(document);
+			assert_eq!(
+				poe.create_claim(&mut system, &mut balances, 0, "dave", claim, String::new(), None),
+				Ok(())
+			);
+		}
+		assert_eq!(
+			poe.approve_action(
+				&mut system,
+				&mut balances,
+				"bob",
+				hello,
+				JointAction::Transfer { new_owner: "dave" }
+			),
+			Err(Error::TooManyClaims)
+		);
+
+		// Approving the revoke bob proposed meets the threshold, so it is carried out.
 		assert_eq!(
-			poe.create_claim(&"bob", &"Hello, world!"),
-			Err("this content is already claimed")
+			poe.approve_action(&mut system, &mut balances, "alice", hello, JointAction::Revoke),
+			Ok(())
 		);
-		assert_eq!(poe.revoke_claim(&"alice", &"Hello, world!"), Ok(()));
-		assert_eq!(poe.create_claim(&"bob", &"Hello, world!"), Ok(()));
+		assert_eq!(poe.get_claim(&system, &hello), None);
 	}
 }
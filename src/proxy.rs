@@ -0,0 +1,438 @@
+use crate::{
+	support::{call_hash, DispatchResult, Hash},
+	system,
+};
+use num::{CheckedSub, Zero};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub trait Config:
+	system::Config<RuntimeEvent: From<Event<Self::AccountId, Self::BlockNumber>>>
+{
+	/// The calls of every pallet in the runtime, which a proxy can dispatch for its real account.
+	type RuntimeCall: Serialize;
+	/// The most proxies a single account can have.
+	const MAX_PROXIES: usize;
+	/// The most announcements a proxy can have pending at once.
+	const MAX_PENDING: usize;
+
+	/// Whether a proxy of `proxy_type` may dispatch `call`.
+	fn filter(proxy_type: ProxyType, call: &Self::RuntimeCall) -> bool;
+}
+
+/// What a proxy is allowed to do on behalf of its real account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProxyType {
+	Any,
+	/// Only transfer the native balance of the real account with `Transfer` or
+	/// `TransferKeepAlive`. Approved, asset and vested transfers are not included.
+	Transfer,
+	/// Only make and manage proof of existence claims.
+	Claims,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Event<AccountId, BlockNumber> {
+	ProxyAdded {
+		real: AccountId,
+		delegate: AccountId,
+		proxy_type: ProxyType,
+		delay: BlockNumber,
+	},
+	ProxyRemoved {
+		real: AccountId,
+		delegate: AccountId,
+		proxy_type: ProxyType,
+		delay: BlockNumber,
+	},
+	/// `delegate` announced it will dispatch the call with `call_hash` for `real`.
+	Announced {
+		real: AccountId,
+		delegate: AccountId,
+		call_hash: Hash,
+	},
+	/// A proxy dispatched a call for its real account.
+	ProxyExecuted {
+		result: Result<(), String>,
+	},
+}
+
+/// An account allowed to dispatch calls for another. Proxies with a `delay` must announce each
+/// call, and can only dispatch it `delay` blocks later, giving the real account time to reject it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxyDefinition<AccountId, BlockNumber> {
+	pub delegate: AccountId,
+	pub proxy_type: ProxyType,
+	pub delay: BlockNumber,
+}
+
+/// A call a delayed proxy intends to dispatch for `real`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Announcement<AccountId, BlockNumber> {
+	pub real: AccountId,
+	pub call_hash: Hash,
+	/// The block the announcement was made in.
+	pub height: BlockNumber,
+}
+
+/// Accounts which can dispatch calls on behalf of other accounts, restricted by the type of
+/// proxy.
+///
+/// Like sudo and multisig, the runtime dispatches the call once this pallet allowed it.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "T::AccountId: Serialize + DeserializeOwned, T::BlockNumber: Serialize + \
+	DeserializeOwned")]
+pub struct Pallet<T: Config> {
+	/// The proxies of each real account.
+	#[serde(with = "crate::support::storage_map")]
+	#[allow(clippy::type_complexity)]
+	proxies: BTreeMap<T::AccountId, Vec<ProxyDefinition<T::AccountId, T::BlockNumber>>>,
+	/// The pending announcements of each delegate.
+	#[serde(with = "crate::support::storage_map")]
+	#[allow(clippy::type_complexity)]
+	announcements: BTreeMap<T::AccountId, Vec<Announcement<T::AccountId, T::BlockNumber>>>,
+}
+
+impl<T: Config> Pallet<T> {
+	pub fn new() -> Self {
+		Self { proxies: BTreeMap::new(), announcements: BTreeMap::new() }
+	}
+
+	#[cfg(test)]
+	pub fn proxies(
+		&self,
+		real: &T::AccountId,
+	) -> Vec<ProxyDefinition<T::AccountId, T::BlockNumber>> {
+		self.proxies.get(real).cloned().unwrap_or_default()
+	}
+
+	#[cfg(test)]
+	pub fn announcements(
+		&self,
+		delegate: &T::AccountId,
+	) -> Vec<Announcement<T::AccountId, T::BlockNumber>> {
+		self.announcements.get(delegate).cloned().unwrap_or_default()
+	}
+
+	/// Allow `delegate` to dispatch the calls `proxy_type` allows on behalf of `real`.
+	pub fn add_proxy(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		real: T::AccountId,
+		delegate: T::AccountId,
+		proxy_type: ProxyType,
+		delay: T::BlockNumber,
+	) -> DispatchResult {
+		if real == delegate {
+			return Err("an account cannot be its own proxy");
+		}
+		let proxy = ProxyDefinition { delegate: delegate.clone(), proxy_type, delay };
+		let proxies = self.proxies.get(&real).map(Vec::as_slice).unwrap_or_default();
+		if proxies.contains(&proxy) {
+			return Err("proxy already exists");
+		}
+		if proxies.len() >= T::MAX_PROXIES {
+			return Err("too many proxies");
+		}
+		// `real` cannot be reaped while someone can still act for it.
+		system.inc_consumers(&real)?;
+		self.proxies.entry(real.clone()).or_default().push(proxy);
+		system.deposit_event(Event::ProxyAdded { real, delegate, proxy_type, delay });
+		Ok(())
+	}
+
+	pub fn remove_proxy(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		real: T::AccountId,
+		delegate: T::AccountId,
+		proxy_type: ProxyType,
+		delay: T::BlockNumber,
+	) -> DispatchResult {
+		let proxy = ProxyDefinition { delegate: delegate.clone(), proxy_type, delay };
+		let proxies = self.proxies.get_mut(&real).ok_or("proxy not found")?;
+		let index = proxies.iter().position(|p| *p == proxy).ok_or("proxy not found")?;
+		proxies.remove(index);
+		if proxies.is_empty() {
+			self.proxies.remove(&real);
+		}
+		system.dec_consumers(&real);
+		system.deposit_event(Event::ProxyRemoved { real, delegate, proxy_type, delay });
+		Ok(())
+	}
+
+	/// Check `delegate` may dispatch `call` for `real` right away, i.e. it is a proxy of `real`
+	/// without a delay, and of a type which allows `call`.
+	pub fn ensure_proxy(
+		&self,
+		delegate: &T::AccountId,
+		real: &T::AccountId,
+		call: &T::RuntimeCall,
+	) -> DispatchResult {
+		let delays = self.allowed_delays(delegate, real, call)?;
+		if !delays.iter().any(|delay| delay.is_zero()) {
+			return Err("delayed proxies must announce calls first");
+		}
+		Ok(())
+	}
+
+	/// Announce that `delegate` will dispatch the call with `call_hash` for `real`, which it can
+	/// do with `proxy_announced` once the delay of its proxy has passed.
+	pub fn announce(
+		&mut self,
+		system: &mut system::Pallet<T>,
+		delegate: T::AccountId,
+		real: T::AccountId,
+		call_hash: Hash,
+	) -> DispatchResult {
+		if !self.is_proxy(&delegate, &real) {
+			return Err("proxy not found");
+		}
+		let announcements = self.announcements.entry(delegate.clone()).or_default();
+		if announcements.len() >= T::MAX_PENDING {
+			return Err("too many pending announcements");
+		}
+		let height = system.get_block_number();
+		announcements.push(Announcement { real: real.clone(), call_hash, height });
+		system.deposit_event(Event::Announced { real, delegate, call_hash });
+		Ok(())
+	}
+
+	/// Remove an announcement `delegate` made for `real`, so the call can no longer be
+	/// dispatched.
+	pub fn reject_announcement(
+		&mut self,
+		real: &T::AccountId,
+		delegate: &T::AccountId,
+		call_hash: Hash,
+	) -> DispatchResult {
+		self.take_announcement(delegate, |announcement| {
+			announcement.real == *real && announcement.call_hash == call_hash
+		})
+		.map(|_| ())
+	}
+
+	/// Check `delegate` announced `call` for `real`, and the delay of a proxy which allows `call`
+	/// has passed since. The announcement is consumed, so the runtime can dispatch `call` once.
+	pub fn proxy_announced(
+		&mut self,
+		system: &system::Pallet<T>,
+		delegate: &T::AccountId,
+		real: &T::AccountId,
+		call: &T::RuntimeCall,
+	) -> DispatchResult {
+		let call_hash = call_hash(call)?;
+		let announcement = self
+			.announcements
+			.get(delegate)
+			.into_iter()
+			.flatten()
+			.find(|announcement| announcement.real == *real && announcement.call_hash == call_hash)
+			.ok_or("announcement not found")?
+			.clone();
+		let elapsed = system.get_block_number().checked_sub(&announcement.height);
+		let delays = self.allowed_delays(delegate, real, call)?;
+		if !delays.iter().any(|delay| elapsed.is_some_and(|elapsed| elapsed >= *delay)) {
+			return Err("announcement is not yet due");
+		}
+
+		self.take_announcement(delegate, |pending| *pending == announcement)?;
+		Ok(())
+	}
+
+	fn is_proxy(&self, delegate: &T::AccountId, real: &T::AccountId) -> bool {
+		self.proxies
+			.get(real)
+			.into_iter()
+			.flatten()
+			.any(|proxy| proxy.delegate == *delegate)
+	}
+
+	/// The delays of the proxies `delegate` has for `real` whose type allows `call`.
+	fn allowed_delays(
+		&self,
+		delegate: &T::AccountId,
+		real: &T::AccountId,
+		call: &T::RuntimeCall,
+	) -> Result<Vec<T::BlockNumber>, &'static str> {
+		if !self.is_proxy(delegate, real) {
+			return Err("proxy not found");
+		}
+		let delays: Vec<_> = self.proxies[real]
+			.iter()
+			.filter(|proxy| proxy.delegate == *delegate && T::filter(proxy.proxy_type, call))
+			.map(|proxy| proxy.delay)
+			.collect();
+		if delays.is_empty() {
+			return Err("call is not allowed for this proxy type");
+		}
+		Ok(delays)
+	}
+
+	fn take_announcement(
+		&mut self,
+		delegate: &T::AccountId,
+		matches: impl Fn(&Announcement<T::AccountId, T::BlockNumber>) -> bool,
+	) -> Result<Announcement<T::AccountId, T::BlockNumber>, &'static str> {
+		let announcements = self.announcements.get_mut(delegate).ok_or("announcement not found")?;
+		let index = announcements.iter().position(matches).ok_or("announcement not found")?;
+		let announcement = announcements.remove(index);
+		if announcements.is_empty() {
+			self.announcements.remove(delegate);
+		}
+		Ok(announcement)
+	}
+}
+
+#[derive(Serialize)]
+#[serde(bound = "T::AccountId: Serialize, T::BlockNumber: Serialize")]
+pub enum Call<T: Config> {
+	AddProxy {
+		delegate: T::AccountId,
+		proxy_type: ProxyType,
+		delay: T::BlockNumber,
+	},
+	RemoveProxy {
+		delegate: T::AccountId,
+		proxy_type: ProxyType,
+		delay: T::BlockNumber,
+	},
+	/// Dispatch `call` signed by `real`, which the caller is a proxy of.
+	Proxy {
+		real: T::AccountId,
+		call: Box<T::RuntimeCall>,
+	},
+	Announce {
+		real: T::AccountId,
+		call_hash: Hash,
+	},
+	RejectAnnouncement {
+		delegate: T::AccountId,
+		call_hash: Hash,
+	},
+	/// Dispatch a call `delegate` announced for `real`, once the delay has passed. Anyone can
+	/// make this call.
+	ProxyAnnounced {
+		delegate: T::AccountId,
+		real: T::AccountId,
+		call: Box<T::RuntimeCall>,
+	},
+}
+
+#[cfg(test)]
+mod proxy_tests {
+	use super::{Event, Pallet, ProxyDefinition, ProxyType};
+	use crate::{support::call_hash, system};
+
+	crate::support::test_runtime!(&'static str, Proxy(Event<&'static str, u32>));
+	impl super::Config for TestConfig {
+		type RuntimeCall = &'static str;
+		const MAX_PROXIES: usize = 2;
+		const MAX_PENDING: usize = 1;
+
+		fn filter(proxy_type: ProxyType, call: &&'static str) -> bool {
+			match proxy_type {
+				ProxyType::Any => true,
+				ProxyType::Transfer => call.starts_with("transfer"),
+				ProxyType::Claims => call.starts_with("claim"),
+			}
+		}
+	}
+
+	#[test]
+	fn proxy_types_filter_calls() {
+		let mut system = system::Pallet::<TestConfig>::new();
+		let mut proxy = Pallet::<TestConfig>::new();
+
+		assert_eq!(proxy.ensure_proxy(&"hot", &"cold", &"transfer"), Err("proxy not found"));
+		// Proxies keep the real account alive, so it has to exist.
+		assert_eq!(
+			proxy.add_proxy(&mut system, "cold", "hot", ProxyType::Any, 0),
+			Err("account has no providers")
+		);
+		system.inc_providers(&"cold");
+		assert_eq!(
+			proxy.add_proxy(&mut system, "cold", "cold", ProxyType::Any, 0),
+			Err("an account cannot be its own proxy")
+		);
+		assert_eq!(proxy.add_proxy(&mut system, "cold", "hot", ProxyType::Transfer, 0), Ok(()));
+		assert_eq!(
+			proxy.add_proxy(&mut system, "cold", "hot", ProxyType::Transfer, 0),
+			Err("proxy already exists")
+		);
+
+		assert_eq!(proxy.ensure_proxy(&"hot", &"cold", &"transfer"), Ok(()));
+		assert_eq!(
+			proxy.ensure_proxy(&"hot", &"cold", &"claim"),
+			Err("call is not allowed for this proxy type")
+		);
+		assert_eq!(proxy.add_proxy(&mut system, "cold", "hot", ProxyType::Claims, 0), Ok(()));
+		assert_eq!(proxy.ensure_proxy(&"hot", &"cold", &"claim"), Ok(()));
+		assert_eq!(
+			proxy.add_proxy(&mut system, "cold", "hot", ProxyType::Any, 0),
+			Err("too many proxies")
+		);
+		assert_eq!(system.account(&"cold").consumers, 2);
+		assert!(!system.can_dec_provider(&"cold"));
+
+		assert_eq!(proxy.remove_proxy(&mut system, "cold", "hot", ProxyType::Transfer, 0), Ok(()));
+		assert_eq!(system.account(&"cold").consumers, 1);
+		assert_eq!(
+			proxy.proxies(&"cold"),
+			vec![ProxyDefinition { delegate: "hot", proxy_type: ProxyType::Claims, delay: 0 }]
+		);
+		assert_eq!(
+			proxy.ensure_proxy(&"hot", &"cold", &"transfer"),
+			Err("call is not allowed for this proxy type")
+		);
+		assert_eq!(
+			system.events().last(),
+			Some(&TestEvent::Proxy(Event::ProxyRemoved {
+				real: "cold",
+				delegate: "hot",
+				proxy_type: ProxyType::Transfer,
+				delay: 0
+			}))
+		);
+	}
+
+	#[test]
+	fn delayed_proxies_announce_calls() {
+		let mut system = system::Pallet::<TestConfig>::new();
+		let mut proxy = Pallet::<TestConfig>::new();
+		let transfer = call_hash(&"transfer").unwrap();
+		system.inc_providers(&"cold");
+
+		assert_eq!(proxy.add_proxy(&mut system, "cold", "hot", ProxyType::Any, 2), Ok(()));
+		assert_eq!(
+			proxy.ensure_proxy(&"hot", &"cold", &"transfer"),
+			Err("delayed proxies must announce calls first")
+		);
+		assert_eq!(
+			proxy.proxy_announced(&system, &"hot", &"cold", &"transfer"),
+			Err("announcement not found")
+		);
+
+		assert_eq!(proxy.announce(&mut system, "hot", "cold", transfer), Ok(()));
+		assert_eq!(
+			proxy.announce(&mut system, "hot", "cold", transfer),
+			Err("too many pending announcements")
+		);
+		system.set_block_number();
+		assert_eq!(
+			proxy.proxy_announced(&system, &"hot", &"cold", &"transfer"),
+			Err("announcement is not yet due")
+		);
+		system.set_block_number();
+		assert_eq!(proxy.proxy_announced(&system, &"hot", &"cold", &"transfer"), Ok(()));
+		assert!(proxy.announcements(&"hot").is_empty());
+
+		// The real account can reject an announcement before it is due.
+		assert_eq!(proxy.announce(&mut system, "hot", "cold", transfer), Ok(()));
+		assert_eq!(proxy.reject_announcement(&"cold", &"hot", transfer), Ok(()));
+		assert_eq!(
+			proxy.reject_announcement(&"cold", &"hot", transfer),
+			Err("announcement not found")
+		);
+	}
+}
@@ -0,0 +1,116 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fs::File, io, path::Path};
+
+/// A point in time copy of the runtime state, along with the header of the last executed block.
+///
+/// Snapshots are stored as JSON, so they can be attached to bug reports and read by hand.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot<Header, State> {
+	pub header: Header,
+	pub state: State,
+}
+
+/// Write a snapshot of `state` to the file at `path`, replacing any existing file.
+pub fn export<Header, State>(
+	path: impl AsRef<Path>,
+	header: &Header,
+	state: &State,
+) -> io::Result<()>
+where
+	Header: Serialize,
+	State: Serialize,
+{
+	let file = File::create(path)?;
+	serde_json::to_writer_pretty(file, &Snapshot { header, state })?;
+	Ok(())
+}
+
+/// Read back a snapshot previously written with `export`.
+pub fn import<Header, State>(path: impl AsRef<Path>) -> io::Result<Snapshot<Header, State>>
+where
+	Header: DeserializeOwned,
+	State: DeserializeOwned,
+{
+	let file = File::open(path)?;
+	Ok(serde_json::from_reader(io::BufReader::new(file))?)
+}
+
+#[cfg(test)]
+mod snapshot_tests {
+	use crate::{
+		balances, proof_of_existence,
+		support::{sha256, Hash, Header},
+		system,
+	};
+	use serde::{Deserialize, Serialize};
+
+	#[derive(Debug)]
+	struct TestConfig;
+	impl system::Config for TestConfig {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		const BLOCK_HASH_COUNT: u32 = 250;
+		type RuntimeEvent = crate::system::Event<String>;
+	}
+	impl balances::Config for TestConfig {
+		type Balance = u128;
+		const EXISTENTIAL_DEPOSIT: u128 = 1;
+	}
+	impl proof_of_existence::Config for TestConfig {
+		type Content = Hash;
+		type Currency = balances::Pallet<Self>;
+		const CLAIM_DEPOSIT: u128 = 10;
+		const MAX_EXPIRIES_PER_BLOCK: usize = 10;
+		const MAX_CLAIMS_PER_OWNER: usize = 10;
+
+		fn hash(document: &[u8]) -> Hash {
+			sha256(document)
+		}
+	}
+
+	#[derive(Debug, Serialize, Deserialize)]
+	struct State {
+		system: system::Pallet<TestConfig>,
+		balances: balances::Pallet<TestConfig>,
+		proof_of_existence: proof_of_existence::Pallet<TestConfig>,
+	}
+
+	#[test]
+	fn export_import_round_trip() {
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+
+		let mut state = State {
+			system: system::Pallet::new(),
+			balances: balances::Pallet::new(),
+			proof_of_existence: proof_of_existence::Pallet::new(),
+		};
+		state.system.set_block_number();
+		assert_eq!(state.balances.set_balance(&mut state.system, &alice, 70), Ok(()));
+		state.system.inc_nonce(&alice);
+		assert_eq!(state.balances.set_balance(&mut state.system, &bob, 30), Ok(()));
+		// Non-string keys must survive the trip through JSON too.
+		let claim = sha256(b"document");
+		let poe = &mut state.proof_of_existence;
+		let (system, balances) = (&mut state.system, &mut state.balances);
+		assert_eq!(
+			poe.create_claim(system, balances, 0, alice.clone(), claim, String::new(), None),
+			Ok(())
+		);
+
+		let path = std::env::temp_dir().join("rust-state-machine-snapshot-test.json");
+		super::export(&path, &Header { block_number: 1u32 }, &state).unwrap();
+		let snapshot = super::import::<Header<u32>, State>(&path).unwrap();
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(snapshot.header, Header { block_number: 1 });
+		assert_eq!(format!("{:?}", snapshot.state), format!("{:?}", state));
+	}
+
+	#[test]
+	fn import_missing_file_fails() {
+		let path = std::env::temp_dir().join("rust-state-machine-missing-snapshot.json");
+		assert!(super::import::<Header<u32>, State>(&path).is_err());
+	}
+}
@@ -0,0 +1,148 @@
+use serde_json::Value;
+use std::{collections::BTreeSet, fmt};
+
+/// How a single storage entry changed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+	Inserted { new: Value },
+	Modified { old: Value, new: Value },
+	Removed { old: Value },
+}
+
+/// A change to one storage entry of one pallet.
+///
+/// `key` is set for entries of a storage map, and is `None` for plain storage values like
+/// `block_number`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageChange {
+	pub pallet: String,
+	pub storage: String,
+	pub key: Option<String>,
+	pub change: Change,
+}
+
+/// Every storage change between two states of the runtime, e.g. before and after a block.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StateDiff {
+	pub changes: Vec<StorageChange>,
+}
+
+impl StateDiff {
+	/// Compute the diff between two serialized runtime states.
+	///
+	/// Both states are expected to be JSON objects of pallets, where each pallet is an object of
+	/// its storage items, as produced by serializing the `Runtime`. Storage items which are
+	/// themselves objects are storage maps, and are compared key by key.
+	pub fn between(before: &Value, after: &Value) -> Self {
+		let mut changes = vec![];
+		for (pallet, before, after) in zip_objects(before, after) {
+			for (storage, before, after) in zip_objects(before, after) {
+				let entries = match (before, after) {
+					(Value::Object(_), Value::Object(_)) => zip_objects(before, after)
+						.into_iter()
+						.map(|(key, before, after)| (Some(key), before, after))
+						.collect(),
+					_ => vec![(None, before, after)],
+				};
+				for (key, before, after) in entries {
+					let change = match (before, after) {
+						(old, new) if old == new => continue,
+						(Value::Null, new) => Change::Inserted { new: new.clone() },
+						(old, Value::Null) => Change::Removed { old: old.clone() },
+						(old, new) => Change::Modified { old: old.clone(), new: new.clone() },
+					};
+					changes.push(StorageChange {
+						pallet: pallet.clone(),
+						storage: storage.clone(),
+						key,
+						change,
+					});
+				}
+			}
+		}
+		Self { changes }
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.changes.is_empty()
+	}
+}
+
+/// Pair up the fields of two JSON objects by name. A field missing on one side is paired with
+/// `Value::Null`, and anything which is not an object is treated as having no fields.
+fn zip_objects<'a>(before: &'a Value, after: &'a Value) -> Vec<(String, &'a Value, &'a Value)> {
+	let fields = |value: &'a Value| value.as_object().into_iter().flat_map(|map| map.keys());
+	let keys = fields(before).chain(fields(after)).collect::<BTreeSet<_>>();
+	keys.into_iter().map(|key| (key.clone(), &before[key], &after[key])).collect()
+}
+
+impl fmt::Display for StateDiff {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.is_empty() {
+			return writeln!(f, "\t(no changes)");
+		}
+		for StorageChange { pallet, storage, key, change } in &self.changes {
+			write!(f, "\t{}::{}", pallet, storage)?;
+			if let Some(key) = key {
+				write!(f, "[{}]", key)?;
+			}
+			match change {
+				Change::Inserted { new } => writeln!(f, ": inserted {}", new)?,
+				Change::Modified { old, new } => writeln!(f, ": {} -> {}", old, new)?,
+				Change::Removed { old } => writeln!(f, ": removed {}", old)?,
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod state_diff_tests {
+	use super::{Change, StateDiff, StorageChange};
+	use serde_json::json;
+
+	fn change(storage: &str, key: Option<&str>, change: Change) -> StorageChange {
+		StorageChange {
+			pallet: "balances".to_string(),
+			storage: storage.to_string(),
+			key: key.map(|key| key.to_string()),
+			change,
+		}
+	}
+
+	#[test]
+	fn diff_storage_values_and_maps() {
+		let before = json!({
+			"balances": {
+				"total": 100,
+				"balances": { "alice": 100, "bob": 0 },
+			},
+		});
+		let after = json!({
+			"balances": {
+				"total": 100,
+				"balances": { "alice": 70, "charlie": 30 },
+			},
+		});
+
+		assert!(StateDiff::between(&before, &before).is_empty());
+		assert_eq!(
+			StateDiff::between(&before, &after).changes,
+			vec![
+				change(
+					"balances",
+					Some("alice"),
+					Change::Modified { old: json!(100), new: json!(70) }
+				),
+				change("balances", Some("bob"), Change::Removed { old: json!(0) }),
+				change("balances", Some("charlie"), Change::Inserted { new: json!(30) }),
+			]
+		);
+
+		let after = json!({ "balances": { "total": 90, "balances": { "alice": 100, "bob": 0 } } });
+		assert_eq!(
+			StateDiff::between(&before, &after).changes,
+			vec![change("total", None, Change::Modified { old: json!(100), new: json!(90) })]
+		);
+	}
+}
@@ -0,0 +1,107 @@
+use crate::{
+	support::{DispatchResult, Origin},
+	system,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+pub trait Config: system::Config<RuntimeEvent: From<Event<Self::AccountId>>> {
+	/// The calls of every pallet in the runtime, which the sudo key can dispatch.
+	type RuntimeCall;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Event<AccountId> {
+	/// A call was dispatched with the `Root` origin by `sudo`.
+	Sudid { result: Result<(), String> },
+	/// A call was dispatched on behalf of another account by `sudo_as`.
+	SudoAsDone { result: Result<(), String> },
+	/// The sudo key was set, replacing `old` if there was one.
+	KeyChanged { old: Option<AccountId>, new: AccountId },
+}
+
+/// A single key account which can dispatch any call with the `Root` origin.
+///
+/// The calls it dispatches belong to other pallets, so the runtime executes sudo calls: this
+/// pallet only stores the key and checks the origin against it.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "T::AccountId: Serialize + DeserializeOwned")]
+pub struct Pallet<T: Config> {
+	key: Option<T::AccountId>,
+}
+
+impl<T: Config> Pallet<T> {
+	pub fn new() -> Self {
+		Self { key: None }
+	}
+
+	#[cfg(test)]
+	pub fn key(&self) -> Option<&T::AccountId> {
+		self.key.as_ref()
+	}
+
+	/// Ensure the origin is signed by the sudo key.
+	pub fn ensure_sudo(&self, origin: Origin<T::AccountId>) -> DispatchResult {
+		let who = origin.ensure_signed()?;
+		if self.key.as_ref() != Some(&who) {
+			return Err("sender must be the sudo key");
+		}
+		Ok(())
+	}
+
+	/// Make `new` the sudo key, e.g. at genesis.
+	pub fn set_key(&mut self, system: &mut system::Pallet<T>, new: T::AccountId) {
+		let old = self.key.replace(new.clone());
+		system.deposit_event(Event::KeyChanged { old, new });
+	}
+}
+
+#[derive(Serialize)]
+#[serde(bound = "T::AccountId: Serialize, T::RuntimeCall: Serialize")]
+pub enum Call<T: Config> {
+	/// Dispatch `call` with the `Root` origin.
+	Sudo {
+		call: Box<T::RuntimeCall>,
+	},
+	/// Dispatch `call` signed by `who`.
+	SudoAs {
+		who: T::AccountId,
+		call: Box<T::RuntimeCall>,
+	},
+	SetKey {
+		new: T::AccountId,
+	},
+}
+
+#[cfg(test)]
+mod sudo_tests {
+	use super::{Event, Pallet};
+	use crate::{support::Origin, system};
+
+	crate::support::test_runtime!(&'static str, Sudo(Event<&'static str>));
+	impl super::Config for TestConfig {
+		type RuntimeCall = ();
+	}
+
+	#[test]
+	fn only_the_key_is_sudo() {
+		let mut system = system::Pallet::<TestConfig>::new();
+		let mut sudo = Pallet::<TestConfig>::new();
+
+		assert_eq!(sudo.ensure_sudo(Origin::Signed("alice")), Err("sender must be the sudo key"));
+		sudo.set_key(&mut system, "alice");
+		assert_eq!(sudo.key(), Some(&"alice"));
+		assert_eq!(sudo.ensure_sudo(Origin::Signed("alice")), Ok(()));
+		assert_eq!(sudo.ensure_sudo(Origin::Signed("bob")), Err("sender must be the sudo key"));
+		assert_eq!(sudo.ensure_sudo(Origin::Root), Err("bad origin"));
+
+		sudo.set_key(&mut system, "bob");
+		assert_eq!(sudo.ensure_sudo(Origin::Signed("alice")), Err("sender must be the sudo key"));
+		assert_eq!(
+			system.events(),
+			[
+				TestEvent::Sudo(Event::KeyChanged { old: None, new: "alice" }),
+				TestEvent::Sudo(Event::KeyChanged { old: Some("alice"), new: "bob" }),
+			]
+		);
+	}
+}
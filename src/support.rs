@@ -1,21 +1,230 @@
+use num::{CheckedAdd, CheckedSub, Zero};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Debug;
+
 pub struct Block<Header, Extrinsic> {
 	pub header: Header,
 	pub extrinsics: Vec<Extrinsic>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Header<BlockNumber> {
 	pub block_number: BlockNumber,
 }
 
+#[derive(Serialize)]
 pub struct Extrinsic<Caller, Call> {
 	pub caller: Caller,
 	pub call: Call,
 }
 
+/// Where a call is dispatched from, which determines what it is allowed to do.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin<AccountId> {
+	/// The most privileged origin, used for administrative calls like minting funds.
+	Root,
+	/// A call made on behalf of the account which signed the extrinsic.
+	Signed(AccountId),
+	/// An unsigned extrinsic, like the inherents a block author includes in every block.
+	None,
+}
+
+impl<AccountId> Origin<AccountId> {
+	/// Ensure the origin is a signed account, and return that account.
+	pub fn ensure_signed(self) -> Result<AccountId, &'static str> {
+		match self {
+			Origin::Signed(who) => Ok(who),
+			_ => Err("bad origin"),
+		}
+	}
+
+	/// Ensure the origin is `None`, i.e. the call came from an unsigned extrinsic.
+	pub fn ensure_none(self) -> DispatchResult {
+		match self {
+			Origin::None => Ok(()),
+			_ => Err("bad origin"),
+		}
+	}
+
+	/// Ensure the origin is `Root`.
+	pub fn ensure_root(self) -> DispatchResult {
+		match self {
+			Origin::Root => Ok(()),
+			_ => Err("bad origin"),
+		}
+	}
+}
+
 pub type DispatchResult = Result<(), &'static str>;
 
+/// A point in time, in milliseconds since the Unix epoch.
+pub type Moment = u64;
+
+/// A fixed size cryptographic digest.
+pub type Hash = [u8; 32];
+
+/// Hash `data` with SHA-256.
+pub fn sha256(data: &[u8]) -> Hash {
+	Sha256::digest(data).into()
+}
+
+/// Hash the JSON encoding of a call, which is how pallets refer to a call before it is dispatched.
+pub fn call_hash(call: &impl Serialize) -> Result<Hash, &'static str> {
+	let encoded = serde_json::to_vec(call).map_err(|_| "failed to encode call")?;
+	Ok(sha256(&encoded))
+}
+
 pub trait Dispatch {
 	type Caller;
 	type Call;
 	fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
 }
+
+/// Whether an operation may take an account below the existential deposit, and so reap it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExistenceRequirement {
+	KeepAlive,
+	AllowDeath,
+}
+
+/// A currency other pallets can move funds with, without depending on a concrete pallet.
+///
+/// Operations which can reap an account take the system pallet, which holds the account's nonce.
+pub trait Currency<T: crate::system::Config> {
+	type Balance: Zero + CheckedAdd + CheckedSub + Copy + PartialOrd + Debug;
+
+	/// The balance of `who` which is free to be spent.
+	fn free_balance(&self, who: &T::AccountId) -> Self::Balance;
+
+	/// Transfer `amount` from the free balance of `from` to `to`.
+	fn transfer(
+		&mut self,
+		system: &mut crate::system::Pallet<T>,
+		from: &T::AccountId,
+		to: &T::AccountId,
+		amount: Self::Balance,
+		existence: ExistenceRequirement,
+	) -> DispatchResult;
+
+	/// Remove `amount` from the free balance of `who`, reducing the total issuance.
+	fn withdraw(
+		&mut self,
+		system: &mut crate::system::Pallet<T>,
+		who: &T::AccountId,
+		amount: Self::Balance,
+		existence: ExistenceRequirement,
+	) -> DispatchResult;
+
+	/// Add `amount` to the free balance of `who`, creating the account if it does not exist yet
+	/// and increasing the total issuance.
+	fn deposit_creating(
+		&mut self,
+		system: &mut crate::system::Pallet<T>,
+		who: &T::AccountId,
+		amount: Self::Balance,
+	) -> DispatchResult;
+}
+
+/// A currency where funds can be reserved, e.g. to hold deposits.
+pub trait ReservableCurrency<T: crate::system::Config>: Currency<T> {
+	/// The balance of `who` which is reserved, and cannot be spent.
+	fn reserved_balance(&self, who: &T::AccountId) -> Self::Balance;
+
+	/// Move `amount` from the free balance of `who` to its reserved balance.
+	fn reserve(&mut self, who: &T::AccountId, amount: Self::Balance) -> DispatchResult;
+
+	/// Move up to `amount` from the reserved balance of `who` back to its free balance, and return
+	/// the part of `amount` which could not be unreserved.
+	fn unreserve(&mut self, who: &T::AccountId, amount: Self::Balance) -> Self::Balance;
+}
+
+/// Serde helpers for the `BTreeMap`s pallets use as storage.
+///
+/// JSON objects only support string keys, but storage maps are keyed by arbitrary types like
+/// `T::AccountId` or `T::Content`. Use with `#[serde(with = "crate::support::storage_map")]`: keys
+/// which serialize to a string are kept as is, and any other key is written as its JSON encoding.
+pub mod storage_map {
+	use serde::{
+		de::{DeserializeOwned, Error as _},
+		ser::{Error as _, SerializeMap},
+		Deserialize, Deserializer, Serialize, Serializer,
+	};
+	use serde_json::Value;
+	use std::collections::BTreeMap;
+
+	pub fn serialize<K, V, S>(map: &BTreeMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		K: Serialize,
+		V: Serialize,
+		S: Serializer,
+	{
+		let mut entries = serializer.serialize_map(Some(map.len()))?;
+		for (key, value) in map {
+			entries.serialize_entry(&encode_key(key).map_err(S::Error::custom)?, value)?;
+		}
+		entries.end()
+	}
+
+	pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error>
+	where
+		K: DeserializeOwned + Ord,
+		V: Deserialize<'de>,
+		D: Deserializer<'de>,
+	{
+		BTreeMap::<String, V>::deserialize(deserializer)?
+			.into_iter()
+			.map(|(key, value)| Ok((decode_key(&key).map_err(D::Error::custom)?, value)))
+			.collect()
+	}
+
+	/// Turn a storage key into the string used as the JSON object key.
+	pub fn encode_key<K: Serialize>(key: &K) -> serde_json::Result<String> {
+		match serde_json::to_value(key)? {
+			Value::String(key) => Ok(key),
+			key => Ok(key.to_string()),
+		}
+	}
+
+	/// The inverse of `encode_key`. We first try to read the key as a plain string, and only then
+	/// as JSON, so string keys which happen to look like JSON are read back unchanged.
+	pub fn decode_key<K: DeserializeOwned>(key: &str) -> serde_json::Result<K> {
+		serde_json::from_value(Value::String(key.to_string()))
+			.or_else(|_| serde_json::from_str(key))
+	}
+}
+
+/// Declare the `TestEvent` and `TestConfig` of a pallet's unit tests: a runtime with the system
+/// pallet and one other pallet, whose events are wrapped in the given variant of `TestEvent`.
+/// The caller still implements the pallet's own `Config` for `TestConfig`.
+#[cfg(test)]
+macro_rules! test_runtime {
+	($account_id:ty, $variant:ident($event:ty)) => {
+		#[derive(Debug, PartialEq)]
+		enum TestEvent {
+			System($crate::system::Event<$account_id>),
+			$variant($event),
+		}
+		impl From<$crate::system::Event<$account_id>> for TestEvent {
+			fn from(event: $crate::system::Event<$account_id>) -> Self {
+				TestEvent::System(event)
+			}
+		}
+		impl From<$event> for TestEvent {
+			fn from(event: $event) -> Self {
+				TestEvent::$variant(event)
+			}
+		}
+
+		struct TestConfig;
+		impl $crate::system::Config for TestConfig {
+			type AccountId = $account_id;
+			type BlockNumber = u32;
+			type Nonce = u32;
+			const BLOCK_HASH_COUNT: u32 = 250;
+			type RuntimeEvent = TestEvent;
+		}
+	};
+}
+#[cfg(test)]
+pub(crate) use test_runtime;
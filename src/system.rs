@@ -1,22 +1,67 @@
-use num::traits::{One, Zero};
+use crate::support::{sha256, DispatchResult, Hash, Origin};
+use num::traits::{CheckedSub, One, Zero};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::ops::AddAssign;
 
 pub trait Config {
-	type BlockNumber: Zero + One + AddAssign + Copy;
+	type BlockNumber: Zero + One + AddAssign + CheckedSub + Copy + Ord;
 	type AccountId: Ord + Clone;
 	type Nonce: Zero + One + Copy;
+	/// How many of the most recent block hashes are kept.
+	const BLOCK_HASH_COUNT: Self::BlockNumber;
+	/// The events of every pallet in the runtime, which system keeps for the current block.
+	type RuntimeEvent: From<Event<Self::AccountId>>;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Event<AccountId> {
+	/// `sender` put a remark on chain with `remark_with_event`. Only its hash is kept.
+	Remarked { sender: AccountId, hash: Hash },
+}
+
+/// Everything system stores about an account.
+///
+/// An account exists while it has providers or sufficients, e.g. a balance above the existential
+/// deposit. Consumers are pallets which rely on the account existing, such as one holding funds in
+/// reserve for it, and the last provider cannot be removed while there are any.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountInfo<Nonce> {
+	pub nonce: Nonce,
+	pub providers: u32,
+	pub consumers: u32,
+	pub sufficients: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "T::BlockNumber: Serialize + DeserializeOwned, T::AccountId: Serialize + \
+	DeserializeOwned, T::Nonce: Serialize + DeserializeOwned, T::RuntimeEvent: Serialize + \
+	DeserializeOwned")]
 pub struct Pallet<T: Config> {
 	block_number: T::BlockNumber,
-	nonce: BTreeMap<T::AccountId, T::Nonce>,
+	#[serde(with = "crate::support::storage_map")]
+	account: BTreeMap<T::AccountId, AccountInfo<T::Nonce>>,
+	/// The hashes of the last `Config::BLOCK_HASH_COUNT` blocks.
+	#[serde(with = "crate::support::storage_map")]
+	block_hash: BTreeMap<T::BlockNumber, Hash>,
+	/// The events deposited during the current block.
+	events: Vec<T::RuntimeEvent>,
+	/// A free-form key/value store, written only by the root `SetStorage` and `KillStorage` calls.
+	/// No pallet reads it, so it holds data for whoever reads the chain, and cannot change how
+	/// the runtime behaves.
+	#[serde(with = "crate::support::storage_map")]
+	storage: BTreeMap<Vec<u8>, Vec<u8>>,
 }
 
 impl<T: Config> Pallet<T> {
 	pub fn new() -> Self {
-		Self { block_number: T::BlockNumber::zero(), nonce: BTreeMap::new() }
+		Self {
+			block_number: T::BlockNumber::zero(),
+			account: BTreeMap::new(),
+			block_hash: BTreeMap::new(),
+			events: Vec::new(),
+			storage: BTreeMap::new(),
+		}
 	}
 
 	pub fn get_block_number(&self) -> T::BlockNumber {
@@ -27,22 +72,200 @@ impl<T: Config> Pallet<T> {
 		self.block_number += T::BlockNumber::one();
 	}
 
+	/// The hash of block `number`, if it is one of the last `Config::BLOCK_HASH_COUNT` blocks.
+	pub fn block_hash(&self, number: T::BlockNumber) -> Option<Hash> {
+		self.block_hash.get(&number).copied()
+	}
+
+	/// The hash of the block before the current one, or all zeros before the first block.
+	pub fn parent_hash(&self) -> Hash {
+		self.block_number
+			.checked_sub(&T::BlockNumber::one())
+			.and_then(|parent| self.block_hash(parent))
+			.unwrap_or_default()
+	}
+
+	/// Record `hash` as the hash of the current block, once it has been executed, and forget
+	/// hashes which are now too old to keep.
+	pub fn note_block_hash(&mut self, hash: Hash) {
+		let number = self.block_number;
+		self.block_hash.insert(number, hash);
+		if let Some(oldest_pruned) = number.checked_sub(&T::BLOCK_HASH_COUNT) {
+			self.block_hash.retain(|n, _| *n > oldest_pruned);
+		}
+	}
+
+	pub fn account(&self, who: &T::AccountId) -> AccountInfo<T::Nonce> {
+		self.account.get(who).copied().unwrap_or(AccountInfo {
+			nonce: T::Nonce::zero(),
+			providers: 0,
+			consumers: 0,
+			sufficients: 0,
+		})
+	}
+
+	pub fn account_exists(&self, who: &T::AccountId) -> bool {
+		let account = self.account(who);
+		account.providers > 0 || account.sufficients > 0
+	}
+
+	pub fn nonce(&self, who: &T::AccountId) -> T::Nonce {
+		self.account(who).nonce
+	}
+
+	/// Increment the nonce of `who`. Nothing is stored for an account which does not exist, so
+	/// the runtime only accepts signed extrinsics from existing accounts.
 	pub fn inc_nonce(&mut self, who: &T::AccountId) {
-		let nonce = *self.nonce.get(&who).unwrap_or(&T::Nonce::zero());
-		let new_nonce = nonce + T::Nonce::one();
-		self.nonce.insert(who.clone(), new_nonce);
+		let mut account = self.account(who);
+		account.nonce = account.nonce + T::Nonce::one();
+		self.update_or_reap(who, account);
+	}
+
+	/// Add a provider to `who`, creating the account if it did not exist.
+	pub fn inc_providers(&mut self, who: &T::AccountId) {
+		let mut account = self.account(who);
+		account.providers += 1;
+		self.account.insert(who.clone(), account);
+	}
+
+	/// Remove a provider from `who`. Once the account has neither providers nor sufficients it is
+	/// reaped, and everything stored for it, including its nonce, is removed.
+	pub fn dec_providers(&mut self, who: &T::AccountId) -> DispatchResult {
+		let mut account = self.account(who);
+		if account.providers == 0 {
+			return Err("account has no providers");
+		}
+		if !self.can_dec_provider(who) {
+			return Err("account is still in use by other pallets");
+		}
+		account.providers -= 1;
+		self.update_or_reap(who, account);
+		Ok(())
+	}
+
+	/// Whether a provider can be removed from `who`, i.e. doing so would not reap an account
+	/// which still has consumers.
+	pub fn can_dec_provider(&self, who: &T::AccountId) -> bool {
+		let account = self.account(who);
+		account.consumers == 0 || account.providers > 1
+	}
+
+	/// Add a consumer to `who`, which stops its last provider from being removed. Only existing
+	/// accounts can have consumers.
+	pub fn inc_consumers(&mut self, who: &T::AccountId) -> DispatchResult {
+		let mut account = self.account(who);
+		if account.providers == 0 {
+			return Err("account has no providers");
+		}
+		account.consumers += 1;
+		self.account.insert(who.clone(), account);
+		Ok(())
+	}
+
+	pub fn dec_consumers(&mut self, who: &T::AccountId) {
+		let mut account = self.account(who);
+		account.consumers = account.consumers.saturating_sub(1);
+		self.update_or_reap(who, account);
+	}
+
+	/// Add a sufficient to `who`, which keeps the account alive on its own, like a provider, but
+	/// does not allow it to have consumers.
+	pub fn inc_sufficients(&mut self, who: &T::AccountId) {
+		let mut account = self.account(who);
+		account.sufficients += 1;
+		self.account.insert(who.clone(), account);
+	}
+
+	pub fn dec_sufficients(&mut self, who: &T::AccountId) {
+		let mut account = self.account(who);
+		account.sufficients = account.sufficients.saturating_sub(1);
+		self.update_or_reap(who, account);
+	}
+
+	fn update_or_reap(&mut self, who: &T::AccountId, account: AccountInfo<T::Nonce>) {
+		if account.providers == 0 && account.sufficients == 0 {
+			self.account.remove(who);
+		} else {
+			self.account.insert(who.clone(), account);
+		}
+	}
+
+	pub fn deposit_event(&mut self, event: impl Into<T::RuntimeEvent>) {
+		self.events.push(event.into());
+	}
+
+	pub fn events(&self) -> &[T::RuntimeEvent] {
+		&self.events
+	}
+
+	/// Forget the events of the previous block, before executing a new one.
+	pub fn reset_events(&mut self) {
+		self.events.clear();
+	}
+
+	/// The raw value stored under `key` by `SetStorage`.
+	pub fn storage(&self, key: &[u8]) -> Option<Vec<u8>> {
+		self.storage.get(key).cloned()
+	}
+
+	/// Dispatch a call to this pallet.
+	pub fn dispatch(&mut self, origin: Origin<T::AccountId>, call: Call) -> DispatchResult {
+		match call {
+			// A remark only has to be included in a block, there is nothing to execute.
+			Call::Remark { data: _ } => {
+				origin.ensure_signed()?;
+			},
+			Call::RemarkWithEvent { data } => {
+				let sender = origin.ensure_signed()?;
+				self.deposit_event(Event::Remarked { sender, hash: sha256(&data) });
+			},
+			Call::SetStorage { items } => {
+				origin.ensure_root()?;
+				self.storage.extend(items);
+			},
+			Call::KillStorage { keys } => {
+				origin.ensure_root()?;
+				for key in keys {
+					self.storage.remove(&key);
+				}
+			},
+		}
+		Ok(())
 	}
 }
 
+#[derive(Serialize)]
+pub enum Call {
+	/// Put arbitrary data on chain.
+	Remark {
+		data: Vec<u8>,
+	},
+	/// Like `Remark`, but also deposit an event with the hash of the data.
+	RemarkWithEvent {
+		data: Vec<u8>,
+	},
+	/// Write each of `items` to the free-form key/value store of this pallet. Root only.
+	SetStorage {
+		items: Vec<(Vec<u8>, Vec<u8>)>,
+	},
+	/// Remove `keys` from the free-form key/value store of this pallet. Root only.
+	KillStorage {
+		keys: Vec<Vec<u8>>,
+	},
+}
+
 #[cfg(test)]
 mod system_test {
-	use super::Pallet;
+	use super::{Call, Event, Pallet};
+	use crate::support::{sha256, Origin};
 
 	struct TestConfig;
 	impl super::Config for TestConfig {
 		type Nonce = u32;
 		type BlockNumber = u32;
 		type AccountId = String;
+		const BLOCK_HASH_COUNT: u32 = 3;
+		type RuntimeEvent = Event<String>;
 	}
 
 	#[test]
@@ -54,8 +277,91 @@ mod system_test {
 		system.set_block_number();
 		assert_eq!(system.get_block_number(), 1);
 
-		assert_eq!(system.nonce.get(&alice), None);
+		// Accounts which do not exist have no nonce, or any other entry, stored.
+		assert_eq!(system.account.get(&alice), None);
+		system.inc_nonce(&alice);
+		system.dec_consumers(&alice);
+		assert_eq!(system.account.get(&alice), None);
+		system.inc_providers(&alice);
+		system.inc_nonce(&alice);
+		assert_eq!(system.account.get(&alice).map(|account| account.nonce), Some(1));
+	}
+
+	#[test]
+	fn account_reference_counting() {
+		let mut system = Pallet::<TestConfig>::new();
+		let alice = "alice".to_string();
+
+		assert!(!system.account_exists(&alice));
+		assert_eq!(system.inc_consumers(&alice), Err("account has no providers"));
+		system.inc_providers(&alice);
 		system.inc_nonce(&alice);
-		assert_eq!(system.nonce.get(&alice), Some(&1));
+		assert!(system.account_exists(&alice));
+
+		// The last provider cannot go while the account has consumers.
+		assert_eq!(system.inc_consumers(&alice), Ok(()));
+		assert!(!system.can_dec_provider(&alice));
+		assert_eq!(system.dec_providers(&alice), Err("account is still in use by other pallets"));
+		system.inc_providers(&alice);
+		assert_eq!(system.dec_providers(&alice), Ok(()));
+		system.dec_consumers(&alice);
+		assert_eq!(system.nonce(&alice), 1);
+
+		// Sufficients keep an account alive too, and it is reaped once nothing references it.
+		system.inc_sufficients(&alice);
+		assert_eq!(system.dec_providers(&alice), Ok(()));
+		assert!(system.account_exists(&alice));
+		system.dec_sufficients(&alice);
+		assert!(!system.account_exists(&alice));
+		assert_eq!(system.account.get(&alice), None);
+		assert_eq!(system.dec_providers(&alice), Err("account has no providers"));
+	}
+
+	#[test]
+	fn block_hash_history() {
+		let mut system = Pallet::<TestConfig>::new();
+		assert_eq!(system.parent_hash(), [0; 32]);
+
+		for number in 1..=5u8 {
+			system.set_block_number();
+			system.note_block_hash([number; 32]);
+		}
+		assert_eq!(system.block_hash(5), Some([5; 32]));
+		assert_eq!(system.block_hash(3), Some([3; 32]));
+		// Only the last `BLOCK_HASH_COUNT` hashes are kept.
+		assert_eq!(system.block_hash(2), None);
+		assert_eq!(system.block_hash.len(), 3);
+
+		system.set_block_number();
+		assert_eq!(system.parent_hash(), [5; 32]);
+	}
+
+	#[test]
+	fn system_calls() {
+		let mut system = Pallet::<TestConfig>::new();
+		let alice = || Origin::Signed("alice".to_string());
+
+		assert_eq!(system.dispatch(alice(), Call::Remark { data: b"hi".to_vec() }), Ok(()));
+		assert!(system.events().is_empty());
+		assert_eq!(
+			system.dispatch(alice(), Call::RemarkWithEvent { data: b"hi".to_vec() }),
+			Ok(())
+		);
+		assert_eq!(
+			system.events(),
+			[Event::Remarked { sender: "alice".to_string(), hash: sha256(b"hi") }]
+		);
+		system.reset_events();
+		assert!(system.events().is_empty());
+
+		let items = vec![(b"key".to_vec(), b"value".to_vec())];
+		let set_storage = || Call::SetStorage { items: items.clone() };
+		assert_eq!(system.dispatch(alice(), set_storage()), Err("bad origin"));
+		assert_eq!(system.dispatch(Origin::Root, set_storage()), Ok(()));
+		assert_eq!(system.storage(b"key"), Some(b"value".to_vec()));
+		let kill_storage = || Call::KillStorage { keys: vec![b"key".to_vec()] };
+		assert_eq!(system.dispatch(alice(), kill_storage()), Err("bad origin"));
+		assert_eq!(system.dispatch(Origin::Root, kill_storage()), Ok(()));
+		assert_eq!(system.storage(b"key"), None);
 	}
 }
@@ -0,0 +1,125 @@
+use crate::support::{DispatchResult, Moment, Origin};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+pub trait Config: crate::system::Config {
+	/// The smallest gap allowed between the timestamps of consecutive blocks.
+	const MINIMUM_PERIOD: Moment;
+}
+
+/// The time of the current block, set by the block author with an inherent `set` extrinsic at the
+/// start of every block.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Pallet<T: Config> {
+	/// The time of the current block, or `None` before the first block.
+	now: Option<Moment>,
+	/// Whether `set` has been called in the current block.
+	did_update: bool,
+	#[serde(skip)]
+	_config: PhantomData<T>,
+}
+
+impl<T: Config> Pallet<T> {
+	pub fn new() -> Self {
+		Self { now: None, did_update: false, _config: PhantomData }
+	}
+
+	/// The time of the current block, or zero before the first block.
+	pub fn now(&self) -> Moment {
+		self.now.unwrap_or(0)
+	}
+
+	/// Set the time of the current block. This must happen exactly once per block, at least
+	/// `Config::MINIMUM_PERIOD` after the time of the previous block.
+	pub fn set(&mut self, now: Moment) -> DispatchResult {
+		if self.did_update {
+			return Err("timestamp must be updated only once in the block");
+		}
+		if self.now.is_some_and(|prev| now < prev.saturating_add(T::MINIMUM_PERIOD)) {
+			return Err("timestamp must increment by at least the minimum period");
+		}
+		self.now = Some(now);
+		self.did_update = true;
+		Ok(())
+	}
+
+	/// Check the timestamp was set in the block being finalized, and allow it to be set in the
+	/// next one.
+	pub fn on_finalize(&mut self) -> DispatchResult {
+		if !std::mem::take(&mut self.did_update) {
+			return Err("timestamp must be updated once in the block");
+		}
+		Ok(())
+	}
+
+	/// Dispatch a call to this pallet. `set` is an inherent, so it must come with the `None`
+	/// origin.
+	pub fn dispatch(&mut self, origin: Origin<T::AccountId>, call: Call) -> DispatchResult {
+		match call {
+			Call::Set { now } => {
+				origin.ensure_none()?;
+				self.set(now)?;
+			},
+		}
+		Ok(())
+	}
+}
+
+#[derive(Serialize)]
+pub enum Call {
+	Set { now: Moment },
+}
+
+#[cfg(test)]
+mod timestamp_tests {
+	use crate::support::Origin;
+
+	struct TestConfig;
+	impl crate::system::Config for TestConfig {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		const BLOCK_HASH_COUNT: u32 = 250;
+		type RuntimeEvent = crate::system::Event<&'static str>;
+	}
+	impl super::Config for TestConfig {
+		const MINIMUM_PERIOD: u64 = 3_000;
+	}
+
+	#[test]
+	fn set_timestamp_once_per_block() {
+		let mut timestamp = super::Pallet::<TestConfig>::new();
+		let set = |now| super::Call::Set { now };
+
+		assert_eq!(timestamp.dispatch(Origin::Signed("alice"), set(1_000)), Err("bad origin"));
+		assert_eq!(timestamp.dispatch(Origin::None, set(1_000)), Ok(()));
+		assert_eq!(timestamp.now(), 1_000);
+		assert_eq!(
+			timestamp.dispatch(Origin::None, set(9_000)),
+			Err("timestamp must be updated only once in the block")
+		);
+		assert_eq!(timestamp.on_finalize(), Ok(()));
+
+		assert_eq!(
+			timestamp.set(3_999),
+			Err("timestamp must increment by at least the minimum period")
+		);
+		assert_eq!(timestamp.set(4_000), Ok(()));
+		assert_eq!(timestamp.on_finalize(), Ok(()));
+		assert_eq!(timestamp.on_finalize(), Err("timestamp must be updated once in the block"));
+	}
+
+	#[test]
+	fn a_zero_timestamp_still_counts() {
+		let mut timestamp = super::Pallet::<TestConfig>::new();
+
+		assert_eq!(timestamp.set(0), Ok(()));
+		assert_eq!(timestamp.on_finalize(), Ok(()));
+		assert_eq!(
+			timestamp.set(0),
+			Err("timestamp must increment by at least the minimum period")
+		);
+		assert_eq!(timestamp.set(3_000), Ok(()));
+	}
+}
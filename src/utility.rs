@@ -0,0 +1,37 @@
+use crate::system;
+use serde::{Deserialize, Serialize};
+
+pub trait Config: system::Config<RuntimeEvent: From<Event>> {
+	/// The calls of every pallet in the runtime, which can be batched.
+	type RuntimeCall;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Event {
+	/// Every call of a batch succeeded.
+	BatchCompleted,
+	/// The call at `index` of a `batch` or `batch_all` failed, so the calls after it were not
+	/// dispatched.
+	BatchInterrupted { index: usize, error: String },
+	/// A `force_batch` finished, but some of its calls failed.
+	BatchCompletedWithErrors,
+	/// The call at `index` of a `force_batch` failed.
+	ItemFailed { index: usize, error: String },
+}
+
+/// Calls which dispatch several other calls with the origin of the caller, in order.
+///
+/// This pallet has no storage. Like sudo, the runtime dispatches the inner calls, and rolls back
+/// a `batch_all` which fails.
+#[derive(Serialize)]
+#[serde(bound = "T::RuntimeCall: Serialize")]
+pub enum Call<T: Config> {
+	/// Dispatch `calls` until one fails. The calls before it are kept, and the batch itself
+	/// succeeds.
+	Batch { calls: Vec<T::RuntimeCall> },
+	/// Dispatch all of `calls`, or none of them: if one fails, every change made by the batch is
+	/// undone and the batch fails with its error.
+	BatchAll { calls: Vec<T::RuntimeCall> },
+	/// Dispatch every one of `calls`, whether or not the ones before it failed.
+	ForceBatch { calls: Vec<T::RuntimeCall> },
+}
@@ -0,0 +1,240 @@
+use crate::{
+	balances::{self, LockIdentifier, Reasons},
+	support::{DispatchResult, Origin},
+	system,
+};
+use num::{CheckedMul, CheckedSub, Zero};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The id of the balance lock this pallet uses to hold back unvested funds.
+const VESTING_ID: LockIdentifier = *b"vesting ";
+
+pub trait Config: balances::Config {
+	/// Convert a number of blocks into a balance, so we can compute how much has vested.
+	fn blocks_to_balance(blocks: Self::BlockNumber) -> Self::Balance;
+}
+
+/// A linear vesting schedule: `locked` funds are unlocked at `per_block` per block, starting
+/// from `starting_block`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VestingInfo<Balance, BlockNumber> {
+	pub locked: Balance,
+	pub per_block: Balance,
+	pub starting_block: BlockNumber,
+}
+
+impl<Balance, BlockNumber> VestingInfo<Balance, BlockNumber>
+where
+	Balance: Zero + CheckedSub + CheckedMul + Copy,
+	BlockNumber: CheckedSub + Copy,
+{
+	/// The amount still locked by this schedule at block `now`.
+	pub fn locked_at(
+		&self,
+		now: BlockNumber,
+		to_balance: impl Fn(BlockNumber) -> Balance,
+	) -> Balance {
+		// Nothing has vested before the starting block.
+		let Some(elapsed) = now.checked_sub(&self.starting_block) else {
+			return self.locked;
+		};
+		to_balance(elapsed)
+			.checked_mul(&self.per_block)
+			.and_then(|vested| self.locked.checked_sub(&vested))
+			.unwrap_or(Balance::zero())
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound = "T::AccountId: Serialize + DeserializeOwned, T::Balance: Serialize + \
+	DeserializeOwned, T::BlockNumber: Serialize + DeserializeOwned")]
+pub struct Pallet<T: Config> {
+	#[serde(with = "crate::support::storage_map")]
+	vesting: BTreeMap<T::AccountId, VestingInfo<T::Balance, T::BlockNumber>>,
+}
+
+impl<T: Config> Pallet<T> {
+	pub fn new() -> Self {
+		Self { vesting: BTreeMap::new() }
+	}
+
+	#[cfg(test)]
+	pub fn vesting(&self, who: &T::AccountId) -> Option<VestingInfo<T::Balance, T::BlockNumber>> {
+		self.vesting.get(who).cloned()
+	}
+
+	/// Unlock whatever the vesting schedule of `who` has released by the current block. Once the
+	/// schedule is fully vested it is removed, along with its lock and its consumer on the account.
+	pub fn vest(
+		&mut self,
+		balances: &mut balances::Pallet<T>,
+		system: &mut system::Pallet<T>,
+		who: &T::AccountId,
+	) -> DispatchResult {
+		let schedule = self.vesting.get(who).ok_or("no vesting schedule")?;
+		let locked = schedule.locked_at(system.get_block_number(), T::blocks_to_balance);
+		if locked.is_zero() {
+			self.vesting.remove(who);
+			balances.remove_lock(VESTING_ID, who);
+			system.dec_consumers(who);
+		} else {
+			balances.set_lock(VESTING_ID, who, locked, Reasons::All);
+		}
+		Ok(())
+	}
+
+	/// Transfer `schedule.locked` from `from` to `to`, locked under the given vesting schedule.
+	/// The schedule holds a consumer on `to`, so the account cannot be reaped until it has vested.
+	pub fn vested_transfer(
+		&mut self,
+		balances: &mut balances::Pallet<T>,
+		system: &mut system::Pallet<T>,
+		from: &T::AccountId,
+		to: &T::AccountId,
+		schedule: VestingInfo<T::Balance, T::BlockNumber>,
+	) -> DispatchResult {
+		if schedule.per_block.is_zero() {
+			return Err("vesting schedule never unlocks");
+		}
+		if self.vesting.contains_key(to) {
+			return Err("account already has a vesting schedule");
+		}
+		balances.transfer(system, from, to, schedule.locked)?;
+		system.inc_consumers(to)?;
+
+		let to = to.clone();
+		self.vesting.insert(to.clone(), schedule);
+		self.vest(balances, system, &to)
+	}
+
+	pub fn dispatch(
+		&mut self,
+		balances: &mut balances::Pallet<T>,
+		system: &mut system::Pallet<T>,
+		origin: Origin<T::AccountId>,
+		call: Call<T>,
+	) -> DispatchResult {
+		let caller = origin.ensure_signed()?;
+		match call {
+			Call::Vest => {
+				self.vest(balances, system, &caller)?;
+			},
+			Call::VestedTransfer { to, schedule } => {
+				self.vested_transfer(balances, system, &caller, &to, schedule)?;
+			},
+		}
+		Ok(())
+	}
+}
+
+#[derive(Serialize)]
+#[serde(bound = "T::AccountId: Serialize, T::Balance: Serialize, T::BlockNumber: Serialize")]
+pub enum Call<T: Config> {
+	Vest,
+	VestedTransfer { to: T::AccountId, schedule: VestingInfo<T::Balance, T::BlockNumber> },
+}
+
+#[cfg(test)]
+mod vesting_tests {
+	use super::{Pallet, VestingInfo, VESTING_ID};
+	use crate::{balances, system};
+
+	struct TestConfig;
+	impl system::Config for TestConfig {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		const BLOCK_HASH_COUNT: u32 = 250;
+		type RuntimeEvent = crate::system::Event<String>;
+	}
+	impl balances::Config for TestConfig {
+		type Balance = u128;
+		const EXISTENTIAL_DEPOSIT: u128 = 1;
+	}
+	impl super::Config for TestConfig {
+		fn blocks_to_balance(blocks: u32) -> u128 {
+			blocks.into()
+		}
+	}
+
+	#[test]
+	fn linear_vesting() {
+		let mut system = system::Pallet::<TestConfig>::new();
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		let mut vesting = Pallet::<TestConfig>::new();
+
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		let charlie = "charlie".to_string();
+		assert_eq!(balances.set_balance(&mut system, &alice, 100), Ok(()));
+
+		// 50 unlocked at 10 per block, starting from block 2.
+		let schedule = VestingInfo { locked: 50, per_block: 10, starting_block: 2 };
+		assert_eq!(
+			vesting.vested_transfer(&mut balances, &mut system, &alice, &bob, schedule.clone()),
+			Ok(())
+		);
+		assert_eq!(balances.balance(&bob), 50);
+		assert_eq!(balances.frozen_balance(&bob, balances::Reasons::Transfer), 50);
+		assert_eq!(balances.transfer(&mut system, &bob, &charlie, 1), Err("funds are locked"));
+		assert_eq!(
+			vesting.vested_transfer(&mut balances, &mut system, &alice, &bob, schedule),
+			Err("account already has a vesting schedule")
+		);
+
+		// Block 4 is two blocks after the start, so 20 has vested.
+		for _ in 0..4 {
+			system.set_block_number();
+		}
+		assert_eq!(vesting.vest(&mut balances, &mut system, &bob), Ok(()));
+		assert_eq!(balances.frozen_balance(&bob, balances::Reasons::Transfer), 30);
+		assert_eq!(balances.transfer(&mut system, &bob, &charlie, 21), Err("funds are locked"));
+		assert_eq!(balances.transfer(&mut system, &bob, &charlie, 20), Ok(()));
+
+		// Once everything has vested, the schedule and the lock are removed.
+		for _ in 0..10 {
+			system.set_block_number();
+		}
+		assert_eq!(vesting.vest(&mut balances, &mut system, &bob), Ok(()));
+		assert_eq!(vesting.vesting(&bob), None);
+		assert!(balances.locks(&bob).iter().all(|lock| lock.id != VESTING_ID));
+		assert_eq!(balances.transfer(&mut system, &bob, &charlie, 30), Ok(()));
+		assert_eq!(vesting.vest(&mut balances, &mut system, &bob), Err("no vesting schedule"));
+	}
+
+	#[test]
+	fn schedule_keeps_account_alive() {
+		let mut system = system::Pallet::<TestConfig>::new();
+		let mut balances = balances::Pallet::<TestConfig>::new();
+		let mut vesting = Pallet::<TestConfig>::new();
+
+		let alice = "alice".to_string();
+		let bob = "bob".to_string();
+		assert_eq!(balances.set_balance(&mut system, &alice, 100), Ok(()));
+
+		let schedule = VestingInfo { locked: 20, per_block: 10, starting_block: 0 };
+		assert_eq!(
+			vesting.vested_transfer(&mut balances, &mut system, &alice, &bob, schedule),
+			Ok(())
+		);
+		assert_eq!(system.account(&bob).consumers, 1);
+
+		// Reaping bob would leave the schedule behind for whoever recreates the account.
+		assert_eq!(
+			balances.burn(&mut system, &bob, 20),
+			Err("account is still in use by other pallets")
+		);
+		assert!(vesting.vesting(&bob).is_some());
+
+		// Once the schedule has fully vested, the account can be reaped.
+		for _ in 0..2 {
+			system.set_block_number();
+		}
+		assert_eq!(vesting.vest(&mut balances, &mut system, &bob), Ok(()));
+		assert_eq!(system.account(&bob).consumers, 0);
+		assert_eq!(balances.burn(&mut system, &bob, 20), Ok(()));
+		assert!(!system.account_exists(&bob));
+		assert_eq!(vesting.vesting(&bob), None);
+	}
+}